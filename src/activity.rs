@@ -0,0 +1,239 @@
+use crate::discovery::DiscoveredAccounts;
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+};
+use solana_sdk::{
+    clock::{UnixTimestamp, SECONDS_PER_DAY},
+    pubkey::Pubkey,
+    signature::Signature,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The most recent transaction seen for an account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CachedActivity {
+    signature: String,
+    block_time: Option<UnixTimestamp>,
+}
+
+/// Most recent transaction per token account, persisted as JSON between runs:
+///
+/// ```json
+/// { "<token account>": { "signature": "<signature>", "block_time": 1700000000 } }
+/// ```
+///
+/// An account whose cached activity is already within the inactivity
+/// threshold is not looked up again: newer activity could only make it more
+/// recent. Other accounts are looked up, asking only for signatures newer than
+/// the cached one, so an account touched since the last run is still seen as
+/// active.
+#[derive(Debug, Clone, Default)]
+pub struct ActivityCache {
+    path: Option<PathBuf>,
+    entries: HashMap<String, CachedActivity>,
+}
+
+impl ActivityCache {
+    /// A cache that is never written to disk.
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Loads the cache at `path`, starting empty if the file does not exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        let entries = if path.exists() {
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read activity cache {}", path.display()))?;
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse activity cache {}", path.display()))?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            entries,
+        })
+    }
+
+    /// Writes the cache back to the file it was loaded from, if any.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let contents = serde_json::to_string_pretty(&self.entries)?;
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write activity cache {}", path.display()))
+    }
+
+    /// Number of cached accounts.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Looks up when each token account in `discovered` was last touched and
+/// records it as [`TokenAccountInfo::last_activity`].
+///
+/// Accounts cached as active within the last `inactive_days` days are kept
+/// without an RPC call. Accounts whose history cannot be fetched, or whose
+/// latest transaction has no block time, are left unknown.
+///
+/// [`TokenAccountInfo::last_activity`]: crate::discovery::TokenAccountInfo::last_activity
+pub async fn fetch_last_activity(
+    rpc_client: &RpcClient,
+    discovered: &mut DiscoveredAccounts,
+    cache: &mut ActivityCache,
+    inactive_days: u64,
+) -> Result<()> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs());
+    let recent_since = now.saturating_sub(inactive_days.saturating_mul(SECONDS_PER_DAY));
+
+    info!(
+        "Fetching last activity of {} token accounts ({} cached)",
+        discovered.token_accounts.len(),
+        cache.len()
+    );
+
+    for token_account in &mut discovered.token_accounts {
+        let key = token_account.address.to_string();
+
+        let cached_block_time = cache.entries.get(&key).and_then(|cached| cached.block_time);
+        if let Some(block_time) = cached_block_time
+            .filter(|&block_time| u64::try_from(block_time).unwrap_or(0) >= recent_since)
+        {
+            token_account.last_activity = Some(block_time);
+            continue;
+        }
+
+        match latest_activity(rpc_client, &token_account.address, cache.entries.get(&key)).await {
+            Ok(Some(activity)) => {
+                token_account.last_activity = activity.block_time;
+                cache.entries.insert(key, activity);
+            }
+            Ok(None) => warn!(
+                "No transaction history found for account {}",
+                token_account.address
+            ),
+            Err(e) => warn!(
+                "Failed to fetch transaction history for account {}: {:#}",
+                token_account.address, e
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the newest transaction touching `address`, asking only for
+/// transactions after `cached` when there is one.
+async fn latest_activity(
+    rpc_client: &RpcClient,
+    address: &Pubkey,
+    cached: Option<&CachedActivity>,
+) -> Result<Option<CachedActivity>> {
+    let until = cached
+        .map(|cached| Signature::from_str(&cached.signature))
+        .transpose()
+        .context("Invalid cached signature")?;
+
+    let signatures = rpc_client
+        .get_signatures_for_address_with_config(
+            address,
+            GetConfirmedSignaturesForAddress2Config {
+                until,
+                limit: Some(1),
+                commitment: Some(rpc_client.commitment()),
+                ..GetConfirmedSignaturesForAddress2Config::default()
+            },
+        )
+        .await?;
+
+    // Signatures are returned newest first
+    Ok(match signatures.into_iter().next() {
+        Some(status) => Some(CachedActivity {
+            signature: status.signature,
+            block_time: status.block_time,
+        }),
+        None => cached.cloned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::token_account;
+
+    fn discovered() -> (DiscoveredAccounts, Pubkey) {
+        let token_account = token_account().build();
+        let address = token_account.address;
+        let discovered = DiscoveredAccounts {
+            token_accounts: vec![token_account],
+            ..DiscoveredAccounts::default()
+        };
+        (discovered, address)
+    }
+
+    fn cache_with(address: Pubkey, block_time: UnixTimestamp) -> ActivityCache {
+        let mut cache = ActivityCache::in_memory();
+        cache.entries.insert(
+            address.to_string(),
+            CachedActivity {
+                signature: Signature::default().to_string(),
+                block_time: Some(block_time),
+            },
+        );
+        cache
+    }
+
+    fn days_ago(days: u64) -> UnixTimestamp {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        (now - days * SECONDS_PER_DAY) as UnixTimestamp
+    }
+
+    #[tokio::test]
+    async fn recent_cached_activity_skips_lookup() {
+        // Every RPC call fails, so a lookup would leave the activity unknown
+        let rpc_client = RpcClient::new_mock("fails".to_string());
+        let (mut discovered, address) = discovered();
+        let block_time = days_ago(3);
+        let mut cache = cache_with(address, block_time);
+
+        fetch_last_activity(&rpc_client, &mut discovered, &mut cache, 30)
+            .await
+            .unwrap();
+
+        assert_eq!(discovered.token_accounts[0].last_activity, Some(block_time));
+    }
+
+    #[tokio::test]
+    async fn stale_cached_activity_is_looked_up() {
+        let rpc_client = RpcClient::new_mock("fails".to_string());
+        let (mut discovered, address) = discovered();
+        let mut cache = cache_with(address, days_ago(60));
+
+        fetch_last_activity(&rpc_client, &mut discovered, &mut cache, 30)
+            .await
+            .unwrap();
+
+        assert_eq!(discovered.token_accounts[0].last_activity, None);
+    }
+}
@@ -0,0 +1,381 @@
+use crate::plan::{AccountPlan, Batch, MintPlan};
+use anyhow::{bail, Result};
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction, instruction::Instruction, message::Message,
+    packet::PACKET_DATA_SIZE, pubkey::Pubkey, signature::SIGNATURE_BYTES,
+    transaction::MAX_TX_ACCOUNT_LOCKS,
+};
+use spl_memo::build_memo;
+
+/// Limits a single cleanup transaction must stay within.
+#[derive(Debug, Clone)]
+pub struct BatchLimits {
+    /// Optional cap on cleanup instructions per transaction, excluding the
+    /// compute budget and memo instructions.
+    pub max_instructions: Option<usize>,
+    /// Maximum number of distinct accounts referenced by a transaction.
+    pub max_accounts: usize,
+    /// Maximum serialized transaction size in bytes.
+    pub max_transaction_size: usize,
+}
+
+impl Default for BatchLimits {
+    fn default() -> Self {
+        Self {
+            max_instructions: None,
+            max_accounts: MAX_TX_ACCOUNT_LOCKS,
+            max_transaction_size: PACKET_DATA_SIZE,
+        }
+    }
+}
+
+/// Prepends the compute budget instructions every cleanup transaction starts
+/// with.
+pub fn with_compute_budget(
+    instructions: &[Instruction],
+    compute_unit_price: u64,
+    compute_unit_limit: u32,
+) -> Vec<Instruction> {
+    let mut transaction_instructions = Vec::with_capacity(instructions.len() + 2);
+    transaction_instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+        compute_unit_price,
+    ));
+    transaction_instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(
+        compute_unit_limit,
+    ));
+    transaction_instructions.extend_from_slice(instructions);
+    transaction_instructions
+}
+
+/// The SPL Memo instruction appended to every cleanup transaction when a memo
+/// is set. It names no signers, so it adds no account besides the Memo
+/// program.
+pub fn memo_instruction(memo: &str) -> Instruction {
+    build_memo(memo.as_bytes(), &[])
+}
+
+/// Serialized size in bytes of a signed transaction paid by `payer` carrying
+/// `instructions` plus the compute budget instructions and the `memo`, if
+/// any, and the number of distinct accounts it references.
+pub fn transaction_footprint(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    memo: Option<&str>,
+) -> (usize, usize) {
+    // The compute budget instruction data has a fixed width, so the values
+    // used here do not affect the size.
+    let mut transaction_instructions = with_compute_budget(instructions, 0, 0);
+    transaction_instructions.extend(memo.map(memo_instruction));
+    let message = Message::new(&transaction_instructions, Some(payer));
+    let signatures = usize::from(message.header.num_required_signatures);
+    let size = short_vec_len(signatures) + signatures * SIGNATURE_BYTES + message.serialize().len();
+    (size, message.account_keys.len())
+}
+
+/// Greedily packs the instructions of every planned account into as few
+/// transactions as the limits allow, preserving order.
+///
+/// An account's instructions are an atomic unit: they always land in the same
+/// transaction, so a burn is never separated from its close.
+pub fn pack_accounts(
+    payer: &Pubkey,
+    accounts: &[AccountPlan],
+    memo: Option<&str>,
+    limits: &BatchLimits,
+) -> Result<Vec<Batch>> {
+    pack(
+        payer,
+        accounts
+            .iter()
+            .map(|account| (account.address, account.instructions.as_slice())),
+        memo,
+        limits,
+    )
+}
+
+/// Packs the instructions of every planned mint the same way as
+/// [`pack_accounts`]. Mints get batches of their own so they are only closed
+/// once the burns emptying them have landed.
+pub fn pack_mints(
+    payer: &Pubkey,
+    mints: &[MintPlan],
+    memo: Option<&str>,
+    limits: &BatchLimits,
+) -> Result<Vec<Batch>> {
+    pack(
+        payer,
+        mints
+            .iter()
+            .map(|mint| (mint.address, mint.instructions.as_slice())),
+        memo,
+        limits,
+    )
+}
+
+fn pack<'a>(
+    payer: &Pubkey,
+    units: impl Iterator<Item = (Pubkey, &'a [Instruction])>,
+    memo: Option<&str>,
+    limits: &BatchLimits,
+) -> Result<Vec<Batch>> {
+    // Otherwise every account would be reported as too large on its own
+    if let Some(memo) = memo.filter(|&memo| !fits(payer, &[], Some(memo), limits)) {
+        bail!(
+            "Memo {:?} ({} bytes) is too long to fit in a transaction",
+            memo,
+            memo.len()
+        );
+    }
+
+    let mut batches = Vec::new();
+    let mut current = Batch::default();
+
+    for (address, instructions) in units.filter(|(_, instructions)| !instructions.is_empty()) {
+        let mut candidate = current.instructions.clone();
+        candidate.extend_from_slice(instructions);
+
+        if !current.instructions.is_empty() && !fits(payer, &candidate, memo, limits) {
+            batches.push(std::mem::take(&mut current));
+            candidate = instructions.to_vec();
+        }
+
+        if !fits(payer, &candidate, memo, limits) {
+            bail!(
+                "Instructions for account {} do not fit in a single transaction",
+                address
+            );
+        }
+
+        current.instructions = candidate;
+        current.accounts.push(address);
+    }
+
+    if !current.instructions.is_empty() {
+        batches.push(current);
+    }
+
+    Ok(batches)
+}
+
+fn fits(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    memo: Option<&str>,
+    limits: &BatchLimits,
+) -> bool {
+    if limits
+        .max_instructions
+        .is_some_and(|max_instructions| instructions.len() > max_instructions)
+    {
+        return false;
+    }
+
+    let (size, accounts) = transaction_footprint(payer, instructions, memo);
+    size <= limits.max_transaction_size && accounts <= limits.max_accounts
+}
+
+/// Length of the compact-u16 prefix used to encode `len`.
+fn short_vec_len(len: usize) -> usize {
+    match len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        discovery::DiscoveredAccounts,
+        planner::{plan_cleanup, PlannerConfig},
+        protection::ProtectionRules,
+        test_support::token_account,
+    };
+    use solana_sdk::{
+        hash::Hash,
+        signature::{Keypair, Signer},
+        transaction::Transaction,
+    };
+
+    const MEMO: &str = "token-cleanup run 3f9a2c1e7b5d4086";
+
+    /// Plans accounts of `owner` in both token programs, every other one
+    /// holding tokens to burn before closing.
+    fn account_plans(owner: &Pubkey, count: u64) -> Vec<AccountPlan> {
+        let token_accounts = (0..count)
+            .map(|i| {
+                let program_id = if i % 3 == 0 {
+                    spl_token_2022::id()
+                } else {
+                    spl_token::id()
+                };
+                token_account()
+                    .owner(owner)
+                    .program_id(&program_id)
+                    .amount(i % 2 * 1_000)
+                    .build()
+            })
+            .collect();
+        let discovered = DiscoveredAccounts {
+            token_accounts,
+            ..DiscoveredAccounts::default()
+        };
+        let config = PlannerConfig {
+            protection: ProtectionRules::empty(),
+            ..PlannerConfig::default()
+        };
+        plan_cleanup(owner, discovered, &config).unwrap().accounts
+    }
+
+    /// The transaction the executor sends for `instructions`.
+    fn signed_transaction(
+        payer: &Keypair,
+        instructions: &[Instruction],
+        memo: Option<&str>,
+    ) -> Transaction {
+        let mut transaction_instructions = with_compute_budget(instructions, 50_000, 200_000);
+        transaction_instructions.extend(memo.map(memo_instruction));
+        Transaction::new_signed_with_payer(
+            &transaction_instructions,
+            Some(&payer.pubkey()),
+            &[payer],
+            Hash::new_unique(),
+        )
+    }
+
+    fn serialized_len(transaction: &Transaction) -> usize {
+        bincode::serialize(transaction).unwrap().len()
+    }
+
+    #[test]
+    fn footprint_matches_signed_transaction() {
+        let payer = Keypair::new();
+        for count in [0, 1, 2, 7] {
+            let accounts = account_plans(&payer.pubkey(), count);
+            let instructions: Vec<_> = accounts
+                .iter()
+                .flat_map(|account| account.instructions.clone())
+                .collect();
+
+            for memo in [None, Some(MEMO)] {
+                let transaction = signed_transaction(&payer, &instructions, memo);
+                assert_eq!(
+                    transaction_footprint(&payer.pubkey(), &instructions, memo),
+                    (
+                        serialized_len(&transaction),
+                        transaction.message.account_keys.len()
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn packed_batches_fit_in_a_packet() {
+        let payer = Keypair::new();
+        let accounts = account_plans(&payer.pubkey(), 60);
+
+        for memo in [None, Some(MEMO)] {
+            let batches =
+                pack_accounts(&payer.pubkey(), &accounts, memo, &BatchLimits::default()).unwrap();
+
+            assert!(batches.len() > 1);
+            for batch in &batches {
+                let transaction = signed_transaction(&payer, &batch.instructions, memo);
+                assert!(serialized_len(&transaction) <= PACKET_DATA_SIZE);
+                assert!(transaction.message.account_keys.len() <= MAX_TX_ACCOUNT_LOCKS);
+            }
+            // Greedy: the next account would not have fitted
+            for pair in batches.windows(2) {
+                let mut instructions = pair[0].instructions.clone();
+                let next = accounts
+                    .iter()
+                    .find(|account| account.address == pair[1].accounts[0])
+                    .unwrap();
+                instructions.extend_from_slice(&next.instructions);
+                let transaction = signed_transaction(&payer, &instructions, memo);
+                assert!(serialized_len(&transaction) > PACKET_DATA_SIZE);
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_account_instructions_together() {
+        let payer = Pubkey::new_unique();
+        let accounts = account_plans(&payer, 40);
+        let limits = BatchLimits {
+            max_instructions: Some(5),
+            ..BatchLimits::default()
+        };
+
+        let batches = pack_accounts(&payer, &accounts, Some(MEMO), &limits).unwrap();
+
+        let mut remaining = accounts.iter();
+        for batch in &batches {
+            assert!(batch.instructions.len() <= 5);
+            let mut instructions = Vec::new();
+            for address in &batch.accounts {
+                let account = remaining.next().unwrap();
+                assert_eq!(*address, account.address);
+                instructions.extend_from_slice(&account.instructions);
+            }
+            assert_eq!(batch.instructions, instructions);
+        }
+        assert!(remaining.next().is_none());
+    }
+
+    #[test]
+    fn skips_accounts_without_instructions() {
+        let payer = Pubkey::new_unique();
+        let mut accounts = account_plans(&payer, 3);
+        accounts[1].instructions.clear();
+
+        let batches = pack_accounts(&payer, &accounts, None, &BatchLimits::default()).unwrap();
+
+        assert_eq!(batches.len(), 1);
+        assert_eq!(
+            batches[0].accounts,
+            vec![accounts[0].address, accounts[2].address]
+        );
+    }
+
+    #[test]
+    fn rejects_account_too_large_for_a_transaction() {
+        let payer = Pubkey::new_unique();
+        let accounts = vec![account_plans(&payer, 2).remove(1)];
+        let limits = BatchLimits {
+            max_instructions: Some(1),
+            ..BatchLimits::default()
+        };
+
+        let error = pack_accounts(&payer, &accounts, None, &limits).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Instructions for account {} do not fit in a single transaction",
+                accounts[0].address
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_memo_too_long_for_a_transaction() {
+        let payer = Pubkey::new_unique();
+        let accounts = account_plans(&payer, 3);
+        let memo = "x".repeat(PACKET_DATA_SIZE);
+
+        let error =
+            pack_accounts(&payer, &accounts, Some(&memo), &BatchLimits::default()).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Memo {:?} ({} bytes) is too long to fit in a transaction",
+                memo, PACKET_DATA_SIZE
+            )
+        );
+        assert!(pack_mints(&payer, &[], Some(&memo), &BatchLimits::default()).is_err());
+    }
+}
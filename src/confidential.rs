@@ -0,0 +1,332 @@
+use crate::discovery::DiscoveredAccounts;
+use log::{info, warn};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Signer};
+use spl_token_2022::{
+    extension::confidential_transfer::{
+        instruction::{apply_pending_balance, empty_account},
+        ConfidentialTransferAccount, EncryptedBalance,
+    },
+    proof::ProofLocation,
+    solana_zk_token_sdk::{
+        encryption::{
+            auth_encryption::AeKey,
+            elgamal::{ElGamalCiphertext, ElGamalKeypair},
+        },
+        instruction::ZeroBalanceProofData,
+        zk_token_elgamal::ops::add_with_lo_hi,
+    },
+};
+use std::num::NonZeroI8;
+
+/// Confidential transfer state of a Token-2022 account, which must hold no
+/// encrypted balance before it can be closed.
+#[derive(Debug, Clone)]
+pub enum ConfidentialBalance {
+    /// Every encrypted balance is already zero, so the account closes as is.
+    Empty,
+    /// Encrypted balances that have not been checked yet, which requires the
+    /// owner's ElGamal key. See [`prepare_confidential_accounts`].
+    Unchecked(Box<ConfidentialTransferAccount>),
+    /// The balances encrypt zero; these instructions apply the pending
+    /// balance and empty the account with a zero-balance proof.
+    Emptiable(Vec<Instruction>),
+    /// The balances cannot be emptied by the wallet.
+    Blocked(String),
+}
+
+impl ConfidentialBalance {
+    /// Classifies the confidential transfer extension of an account.
+    pub fn from_extension(extension: &ConfidentialTransferAccount) -> Self {
+        if extension.closable().is_ok() {
+            ConfidentialBalance::Empty
+        } else {
+            ConfidentialBalance::Unchecked(Box::new(*extension))
+        }
+    }
+}
+
+/// Checks the encrypted balances of every unchecked confidential transfer
+/// account in `discovered` with `signer`'s ElGamal keys, and prepares the
+/// instructions emptying the ones that hold nothing.
+///
+/// Keys are derived from the signer and the token account address, the same
+/// way the `spl-token` CLI configures accounts. Proofs are generated locally.
+pub fn prepare_confidential_accounts(signer: &dyn Signer, discovered: &mut DiscoveredAccounts) {
+    for token_account in &mut discovered.token_accounts {
+        let Some(ConfidentialBalance::Unchecked(extension)) = &token_account.confidential else {
+            continue;
+        };
+
+        let confidential = match empty_instructions(
+            signer,
+            &token_account.program_id,
+            &token_account.address,
+            extension,
+        ) {
+            Ok(instructions) => {
+                info!(
+                    "Prepared confidential balance cleanup for account: {}",
+                    token_account.address
+                );
+                ConfidentialBalance::Emptiable(instructions)
+            }
+            Err(reason) => {
+                warn!(
+                    "Cannot empty confidential balance of account {}: {}",
+                    token_account.address, reason
+                );
+                ConfidentialBalance::Blocked(reason)
+            }
+        };
+        token_account.confidential = Some(confidential);
+    }
+}
+
+/// Builds `ApplyPendingBalance` (if anything is pending) followed by
+/// `EmptyAccount` and its zero-balance proof, describing why the account
+/// cannot be emptied otherwise.
+fn empty_instructions(
+    signer: &dyn Signer,
+    program_id: &Pubkey,
+    address: &Pubkey,
+    extension: &ConfidentialTransferAccount,
+) -> Result<Vec<Instruction>, String> {
+    let owner = signer.pubkey();
+    let elgamal_keypair = ElGamalKeypair::new_from_signer(signer, &address.to_bytes())
+        .map_err(|e| format!("failed to derive ElGamal key: {}", e))?;
+    if elgamal_keypair.pubkey().to_bytes() != extension.elgamal_pubkey.0 {
+        return Err("ElGamal key was not derived from this wallet".to_string());
+    }
+
+    let mut instructions = Vec::new();
+    let zeroed = EncryptedBalance::default();
+    let has_pending =
+        extension.pending_balance_lo != zeroed || extension.pending_balance_hi != zeroed;
+
+    // Applying the pending balance folds it into the available balance the
+    // same way the program does, so the proof covers the resulting ciphertext.
+    let available_balance = if has_pending {
+        let aes_key = AeKey::new_from_signer(signer, &address.to_bytes())
+            .map_err(|e| format!("failed to derive AES key: {}", e))?;
+        instructions.push(
+            apply_pending_balance(
+                program_id,
+                address,
+                u64::from(extension.pending_balance_credit_counter),
+                aes_key.encrypt(0),
+                &owner,
+                &[],
+            )
+            .map_err(|e| e.to_string())?,
+        );
+        add_with_lo_hi(
+            &extension.available_balance,
+            &extension.pending_balance_lo,
+            &extension.pending_balance_hi,
+        )
+        .ok_or("invalid pending balance ciphertext")?
+    } else {
+        extension.available_balance
+    };
+
+    let ciphertext = ElGamalCiphertext::try_from(available_balance)
+        .map_err(|_| "invalid available balance ciphertext".to_string())?;
+    match elgamal_keypair.secret().decrypt_u32(&ciphertext) {
+        Some(0) => {}
+        Some(amount) => {
+            return Err(format!(
+                "holds {} confidential tokens; withdraw them first",
+                amount
+            ))
+        }
+        None => return Err("holds a confidential balance; withdraw it first".to_string()),
+    }
+
+    let proof_data = ZeroBalanceProofData::new(&elgamal_keypair, &ciphertext)
+        .map_err(|e| format!("failed to generate zero-balance proof: {}", e))?;
+    instructions.extend(
+        empty_account(
+            program_id,
+            address,
+            &owner,
+            &[],
+            ProofLocation::InstructionOffset(NonZeroI8::new(1).unwrap(), &proof_data),
+        )
+        .map_err(|e| e.to_string())?,
+    );
+
+    Ok(instructions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::token_account;
+    use solana_sdk::signature::Keypair;
+    use spl_token_2022::{
+        extension::confidential_transfer::instruction::{
+            ConfidentialTransferInstruction, EmptyAccountInstructionData,
+        },
+        instruction::{decode_instruction_data, decode_instruction_type},
+        solana_zk_token_sdk::{
+            instruction::ZkProofData, zk_token_proof_instruction::ProofInstruction,
+            zk_token_proof_program,
+        },
+    };
+
+    /// A confidential account of `owner` whose balances are encrypted under
+    /// `elgamal_keypair`, after preparing it.
+    fn prepare(
+        owner: &Keypair,
+        elgamal_keypair: Option<&ElGamalKeypair>,
+        available: u64,
+        pending: Option<u64>,
+    ) -> (Pubkey, ConfidentialTransferAccount, ConfidentialBalance) {
+        let address = Pubkey::new_unique();
+        let derived = ElGamalKeypair::new_from_signer(owner, &address.to_bytes()).unwrap();
+        let elgamal_keypair = elgamal_keypair.unwrap_or(&derived);
+        let elgamal_pubkey = *elgamal_keypair.pubkey();
+
+        let mut extension = ConfidentialTransferAccount {
+            elgamal_pubkey: elgamal_pubkey.into(),
+            available_balance: elgamal_pubkey.encrypt(available).into(),
+            pending_balance_credit_counter: 2.into(),
+            ..ConfidentialTransferAccount::default()
+        };
+        if let Some(pending) = pending {
+            extension.pending_balance_lo = elgamal_pubkey.encrypt(pending).into();
+            extension.pending_balance_hi = elgamal_pubkey.encrypt(0_u64).into();
+        }
+
+        let mut token_account = token_account()
+            .owner(&owner.pubkey())
+            .program_id(&spl_token_2022::id())
+            .confidential(ConfidentialBalance::Unchecked(Box::new(extension)))
+            .build();
+        token_account.address = address;
+        let mut discovered = DiscoveredAccounts {
+            token_accounts: vec![token_account],
+            ..DiscoveredAccounts::default()
+        };
+
+        prepare_confidential_accounts(owner, &mut discovered);
+
+        let confidential = discovered.token_accounts.remove(0).confidential.unwrap();
+        (address, extension, confidential)
+    }
+
+    /// Checks that `instructions` end with `EmptyAccount` followed by a
+    /// zero-balance proof of `available_balance` that verifies.
+    fn assert_empties(
+        instructions: &[Instruction],
+        address: &Pubkey,
+        owner: &Pubkey,
+        available_balance: &EncryptedBalance,
+    ) {
+        let [.., empty, proof] = instructions else {
+            panic!("expected EmptyAccount and its proof: {:?}", instructions);
+        };
+
+        assert_eq!(empty.program_id, spl_token_2022::id());
+        assert_eq!(empty.accounts[0].pubkey, *address);
+        assert!(matches!(
+            decode_instruction_type(&empty.data[1..]),
+            Ok(ConfidentialTransferInstruction::EmptyAccount)
+        ));
+        let empty_data =
+            decode_instruction_data::<EmptyAccountInstructionData>(&empty.data[1..]).unwrap();
+        assert_eq!(empty_data.proof_instruction_offset, 1);
+        assert!(empty
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == *owner && meta.is_signer));
+
+        assert_eq!(proof.program_id, zk_token_proof_program::id());
+        assert_eq!(
+            ProofInstruction::instruction_type(&proof.data),
+            Some(ProofInstruction::VerifyZeroBalance)
+        );
+        let proof_data =
+            ProofInstruction::proof_data::<ZeroBalanceProofData, _>(&proof.data).unwrap();
+        assert_eq!(proof_data.context_data().ciphertext, *available_balance);
+        proof_data.verify_proof().unwrap();
+    }
+
+    #[test]
+    fn empties_zero_balance() {
+        let owner = Keypair::new();
+
+        let (address, extension, confidential) = prepare(&owner, None, 0, None);
+
+        let ConfidentialBalance::Emptiable(instructions) = confidential else {
+            panic!("expected an emptiable balance: {:?}", confidential);
+        };
+        assert_eq!(instructions.len(), 2);
+        assert_empties(
+            &instructions,
+            &address,
+            &owner.pubkey(),
+            &extension.available_balance,
+        );
+    }
+
+    #[test]
+    fn applies_zero_pending_balance_before_emptying() {
+        let owner = Keypair::new();
+
+        let (address, extension, confidential) = prepare(&owner, None, 0, Some(0));
+
+        let ConfidentialBalance::Emptiable(instructions) = confidential else {
+            panic!("expected an emptiable balance: {:?}", confidential);
+        };
+        assert_eq!(instructions.len(), 3);
+        assert!(matches!(
+            decode_instruction_type(&instructions[0].data[1..]),
+            Ok(ConfidentialTransferInstruction::ApplyPendingBalance)
+        ));
+        let applied = add_with_lo_hi(
+            &extension.available_balance,
+            &extension.pending_balance_lo,
+            &extension.pending_balance_hi,
+        )
+        .unwrap();
+        assert_empties(&instructions, &address, &owner.pubkey(), &applied);
+    }
+
+    #[test]
+    fn blocks_nonzero_available_balance() {
+        let owner = Keypair::new();
+
+        let (_, _, confidential) = prepare(&owner, None, 7, None);
+
+        let ConfidentialBalance::Blocked(reason) = confidential else {
+            panic!("expected a blocked balance: {:?}", confidential);
+        };
+        assert_eq!(reason, "holds 7 confidential tokens; withdraw them first");
+    }
+
+    #[test]
+    fn blocks_nonzero_pending_balance() {
+        let owner = Keypair::new();
+
+        let (_, _, confidential) = prepare(&owner, None, 0, Some(5));
+
+        let ConfidentialBalance::Blocked(reason) = confidential else {
+            panic!("expected a blocked balance: {:?}", confidential);
+        };
+        assert_eq!(reason, "holds 5 confidential tokens; withdraw them first");
+    }
+
+    #[test]
+    fn blocks_key_not_derived_from_signer() {
+        let owner = Keypair::new();
+        let foreign = ElGamalKeypair::new_rand();
+
+        let (_, _, confidential) = prepare(&owner, Some(&foreign), 0, None);
+
+        let ConfidentialBalance::Blocked(reason) = confidential else {
+            panic!("expected a blocked balance: {:?}", confidential);
+        };
+        assert_eq!(reason, "ElGamal key was not derived from this wallet");
+    }
+}
@@ -0,0 +1,660 @@
+use crate::{
+    confidential::ConfidentialBalance,
+    metaplex::MetaplexNft,
+    risk::{mint_risks, MintRisk},
+};
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde_json::json;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+    rpc_request::{RpcRequest, TokenAccountsFilter},
+    rpc_response::{Response, RpcKeyedAccount},
+};
+use solana_sdk::{
+    account::Account,
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::{Pubkey, PUBKEY_BYTES},
+};
+use spl_token_2022::{
+    extension::{
+        confidential_transfer::ConfidentialTransferAccount, AccountType, BaseStateWithExtensions,
+        ExtensionType, StateWithExtensions,
+    },
+    state::{Account as TokenAccount, Mint},
+};
+use std::{collections::HashMap, str::FromStr};
+
+/// Maximum number of accounts accepted by a single `getMultipleAccounts` call.
+pub(crate) const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Token programs whose accounts are discovered: classic SPL Token and
+/// Token-2022 (Token Extensions).
+pub fn token_program_ids() -> [Pubkey; 2] {
+    [spl_token::id(), spl_token_2022::id()]
+}
+
+/// A token account owned by the wallet, as read from the chain.
+#[derive(Debug, Clone)]
+pub struct TokenAccountInfo {
+    /// Address of the token account.
+    pub address: Pubkey,
+    /// Token program that owns the account.
+    pub program_id: Pubkey,
+    /// Lamports held by the account, returned to the wallet when it is closed.
+    pub lamports: u64,
+    /// Decoded base token account state.
+    pub account: TokenAccount,
+    /// Token-2022 extensions present on the account, empty for classic SPL
+    /// Token accounts. Extensions unknown to `spl-token-2022` are left out.
+    pub extensions: Vec<ExtensionType>,
+    /// Transfer fees withheld in the account, which must be harvested to the
+    /// mint before it can be closed. Zero without the `TransferFeeAmount`
+    /// extension.
+    pub withheld_amount: u64,
+    /// Encrypted balances of an account configured for confidential
+    /// transfers, which must be emptied before it can be closed. `None`
+    /// without the `ConfidentialTransferAccount` extension.
+    pub confidential: Option<ConfidentialBalance>,
+    /// The account's mint, if it could be fetched and decoded.
+    pub mint_info: Option<MintInfo>,
+    /// USD price of one whole token, if a price source was consulted and knew
+    /// the mint.
+    pub usd_price: Option<f64>,
+    /// Token Metadata accounts of the NFT held, if it can be burned with
+    /// `BurnV1` to recover their rent as well.
+    pub metaplex: Option<MetaplexNft>,
+    /// Unix time of the most recent transaction touching the account, if it
+    /// was looked up and is known.
+    pub last_activity: Option<i64>,
+}
+
+impl TokenAccountInfo {
+    /// Returns `true` if the account holds an NFT.
+    pub fn is_nft(&self) -> bool {
+        self.mint_info.as_ref().is_some_and(MintInfo::is_nft)
+    }
+}
+
+/// A mint referenced by a discovered token account.
+#[derive(Debug, Clone)]
+pub struct MintInfo {
+    /// Address of the mint.
+    pub address: Pubkey,
+    /// Token program that owns the mint.
+    pub program_id: Pubkey,
+    /// Lamports held by the mint, returned to the close authority when it is
+    /// closed.
+    pub lamports: u64,
+    /// Decoded base mint state.
+    pub mint: Mint,
+    /// Token-2022 extensions present on the mint, empty for classic SPL Token
+    /// mints. Extensions unknown to `spl-token-2022` are left out.
+    pub extensions: Vec<ExtensionType>,
+    /// Extensions that affect burning and closing, or that mark likely scam
+    /// airdrops.
+    pub risks: Vec<MintRisk>,
+    /// Key allowed to close the mint once its supply is zero, set through the
+    /// Token-2022 `MintCloseAuthority` extension.
+    pub close_authority: Option<Pubkey>,
+}
+
+/// An account owned by a token program that could not be read as a token
+/// account. It is skipped rather than aborting discovery.
+#[derive(Debug, Clone)]
+pub struct UnreadableAccount {
+    /// Address of the account.
+    pub address: Pubkey,
+    /// Token program that owns the account.
+    pub program_id: Pubkey,
+    /// Why the account could not be read.
+    pub reason: String,
+}
+
+impl MintInfo {
+    /// Returns `true` if the mint is an NFT: zero decimals and a supply of
+    /// exactly one.
+    pub fn is_nft(&self) -> bool {
+        self.mint.decimals == 0 && self.mint.supply == 1
+    }
+}
+
+/// Everything found for a wallet.
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveredAccounts {
+    /// Token accounts that were decoded successfully.
+    pub token_accounts: Vec<TokenAccountInfo>,
+    /// Accounts that could not be decoded.
+    pub unreadable: Vec<UnreadableAccount>,
+    /// Token-2022 mints the wallet can close, found by
+    /// [`discover_closable_mints`].
+    pub closable_mints: Vec<MintInfo>,
+}
+
+impl DiscoveredAccounts {
+    /// Total number of accounts found, readable or not.
+    pub fn len(&self) -> usize {
+        self.token_accounts.len() + self.unreadable.len()
+    }
+
+    /// Returns `true` if the wallet owns no token program accounts.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Fetches every SPL Token and Token-2022 account owned by `owner`, along
+/// with the mints they hold.
+///
+/// Accounts whose data cannot be decoded are collected as
+/// [`UnreadableAccount`]s instead of failing the whole discovery.
+pub async fn discover_token_accounts(
+    rpc_client: &RpcClient,
+    owner: &Pubkey,
+) -> Result<DiscoveredAccounts> {
+    info!("Fetching token accounts for wallet: {}", owner);
+
+    let mut token_accounts = Vec::new();
+    let mut unreadable = Vec::new();
+    for program_id in token_program_ids() {
+        let keyed_accounts = get_token_accounts_by_owner(
+            rpc_client,
+            owner,
+            TokenAccountsFilter::ProgramId(program_id),
+        )
+        .await
+        .with_context(|| format!("Failed to fetch token accounts for program {}", program_id))?;
+
+        for keyed_account in keyed_accounts {
+            let address = Pubkey::from_str(&keyed_account.pubkey)?;
+            let Some(account) = keyed_account.account.decode::<Account>() else {
+                warn!(
+                    "Skipping unreadable account {}: undecodable RPC data",
+                    address
+                );
+                unreadable.push(UnreadableAccount {
+                    address,
+                    program_id,
+                    reason: "account data could not be decoded from the RPC response".to_string(),
+                });
+                continue;
+            };
+
+            match unpack_token_account(&account.data) {
+                Ok((state, extensions, withheld_amount, confidential)) => {
+                    token_accounts.push(TokenAccountInfo {
+                        address,
+                        program_id,
+                        lamports: account.lamports,
+                        account: state,
+                        extensions,
+                        withheld_amount,
+                        confidential,
+                        mint_info: None,
+                        usd_price: None,
+                        metaplex: None,
+                        last_activity: None,
+                    })
+                }
+                Err(reason) => {
+                    warn!("Skipping unreadable account {}: {}", address, reason);
+                    unreadable.push(UnreadableAccount {
+                        address,
+                        program_id,
+                        reason,
+                    });
+                }
+            }
+        }
+    }
+
+    let mut mints: Vec<_> = token_accounts
+        .iter()
+        .map(|token_account| token_account.account.mint)
+        .collect();
+    mints.sort();
+    mints.dedup();
+
+    let mint_infos = fetch_mints(rpc_client, &mints).await?;
+    for token_account in &mut token_accounts {
+        token_account.mint_info = mint_infos.get(&token_account.account.mint).cloned();
+    }
+
+    Ok(DiscoveredAccounts {
+        token_accounts,
+        unreadable,
+        closable_mints: Vec::new(),
+    })
+}
+
+/// Unpacks token account data into its base state, extension types, withheld
+/// transfer fees and confidential balances, describing why it is unreadable
+/// on failure.
+fn unpack_token_account(
+    data: &[u8],
+) -> std::result::Result<
+    (
+        TokenAccount,
+        Vec<ExtensionType>,
+        u64,
+        Option<ConfidentialBalance>,
+    ),
+    String,
+> {
+    if data.len() < TokenAccount::LEN {
+        return Err(format!(
+            "data is {} bytes, shorter than the {}-byte token account layout",
+            data.len(),
+            TokenAccount::LEN
+        ));
+    }
+
+    // Token-2022 accounts are variable length: the base state is followed by
+    // a TLV list of extensions.
+    let state = StateWithExtensions::<TokenAccount>::unpack(data).map_err(|e| match e {
+        ProgramError::UninitializedAccount => "account is not initialized".to_string(),
+        e => format!("invalid token account data: {}", e),
+    })?;
+    let entries = extension_entries(state.get_tlv_data())?;
+    let extensions = known_extension_types(&entries);
+
+    // Read from the raw entries: the typed lookup gives up at the first
+    // extension type it does not know, which would hide the fees.
+    let withheld_amount = match extension_value(&entries, ExtensionType::TransferFeeAmount) {
+        Some(value) => value
+            .get(..8)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or("malformed extension data: truncated transfer fee amount")?,
+        None => 0,
+    };
+
+    // Decoded from the raw entry for the same reason; a malformed extension
+    // still blocks closing, so it is reported rather than ignored.
+    let confidential =
+        extension_value(&entries, ExtensionType::ConfidentialTransferAccount).map(|value| {
+            match bytemuck::try_from_bytes::<ConfidentialTransferAccount>(value) {
+                Ok(extension) => ConfidentialBalance::from_extension(extension),
+                Err(_) => ConfidentialBalance::Blocked(
+                    "confidential transfer extension could not be decoded".to_string(),
+                ),
+            }
+        });
+
+    Ok((state.base, extensions, withheld_amount, confidential))
+}
+
+/// Fetches and decodes `mints`. Mints that do not exist or cannot be decoded
+/// are left out of the result.
+pub async fn fetch_mints(
+    rpc_client: &RpcClient,
+    mints: &[Pubkey],
+) -> Result<HashMap<Pubkey, MintInfo>> {
+    let mut mint_infos = HashMap::with_capacity(mints.len());
+
+    for chunk in mints.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let accounts = rpc_client
+            .get_multiple_accounts(chunk)
+            .await
+            .context("Failed to fetch mint accounts")?;
+
+        for (address, account) in chunk.iter().zip(accounts) {
+            let Some(account) = account else {
+                warn!("Mint account not found: {}", address);
+                continue;
+            };
+
+            if let Some(mint_info) = decode_mint(address, &account) {
+                mint_infos.insert(*address, mint_info);
+            }
+        }
+    }
+
+    Ok(mint_infos)
+}
+
+/// Finds the Token-2022 mints whose close authority is `owner` and records
+/// them as [`DiscoveredAccounts::closable_mints`].
+///
+/// Mints are searched for with `getProgramAccounts`, matching mints whose
+/// first extension is a `MintCloseAuthority` of `owner`, which is how the
+/// `spl-token` CLI lays out `create-token --enable-close`. The mints of the
+/// wallet's own token accounts are checked as well, wherever the extension
+/// sits. Mints are included whatever their supply; the planner decides which
+/// can be closed.
+pub async fn discover_closable_mints(
+    rpc_client: &RpcClient,
+    owner: &Pubkey,
+    discovered: &mut DiscoveredAccounts,
+) -> Result<()> {
+    info!(
+        "Searching for Token-2022 mints closable by wallet: {}",
+        owner
+    );
+
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(closable_mint_filter(owner))]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(rpc_client.commitment()),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+    let accounts = rpc_client
+        .get_program_accounts_with_config(&spl_token_2022::id(), config)
+        .await
+        .context("Failed to search for closable mints")?;
+
+    let mut mints: Vec<_> = accounts
+        .iter()
+        .filter_map(|(address, account)| decode_mint(address, account))
+        .collect();
+    for mint_info in discovered
+        .token_accounts
+        .iter()
+        .filter_map(|token_account| token_account.mint_info.as_ref())
+    {
+        if !mints.iter().any(|mint| mint.address == mint_info.address) {
+            mints.push(mint_info.clone());
+        }
+    }
+    mints.retain(|mint_info| {
+        mint_info.program_id == spl_token_2022::id() && mint_info.close_authority == Some(*owner)
+    });
+
+    info!("Found {} mints closable by the wallet", mints.len());
+    discovered.closable_mints = mints;
+    Ok(())
+}
+
+/// Matches Token-2022 mints whose first extension is a `MintCloseAuthority`
+/// set to `owner`: the account type, then the extension's type, length and
+/// value, right after the base state padded to the size of a token account.
+fn closable_mint_filter(owner: &Pubkey) -> Memcmp {
+    let mut first_extension = vec![u8::from(AccountType::Mint)];
+    first_extension.extend(u16::from(ExtensionType::MintCloseAuthority).to_le_bytes());
+    first_extension.extend((PUBKEY_BYTES as u16).to_le_bytes());
+    first_extension.extend(owner.to_bytes());
+    Memcmp::new_raw_bytes(TokenAccount::LEN, first_extension)
+}
+
+/// Decodes a mint account, returning `None` (with a warning) if it is not a
+/// valid mint.
+fn decode_mint(address: &Pubkey, account: &Account) -> Option<MintInfo> {
+    let state = match StateWithExtensions::<Mint>::unpack(&account.data) {
+        Ok(state) => state,
+        Err(e) => {
+            warn!("Failed to unpack mint {}: {}", address, e);
+            return None;
+        }
+    };
+    let entries = extension_entries(state.get_tlv_data()).unwrap_or_else(|reason| {
+        warn!("Ignoring extensions of mint {}: {}", address, reason);
+        Vec::new()
+    });
+    let close_authority = extension_value(&entries, ExtensionType::MintCloseAuthority)
+        .and_then(|value| optional_pubkey(value, 0));
+
+    Some(MintInfo {
+        address: *address,
+        program_id: account.owner,
+        lamports: account.lamports,
+        mint: state.base,
+        extensions: known_extension_types(&entries),
+        risks: mint_risks(&entries),
+        close_authority,
+    })
+}
+
+/// Splits Token-2022 TLV extension data into each entry's raw type and value.
+///
+/// Unlike `get_extension_types`, this keeps going past extension types that
+/// `spl-token-2022` does not know, so accounts using newer extensions stay
+/// readable.
+fn extension_entries(tlv_data: &[u8]) -> std::result::Result<Vec<(u16, &[u8])>, String> {
+    let mut entries = Vec::new();
+    let mut rest = tlv_data;
+
+    // Each entry is a u16 type and a u16 length, followed by the value. The
+    // list ends at an uninitialized type or in trailing padding.
+    while rest.len() >= 4 {
+        let extension_type = u16::from_le_bytes([rest[0], rest[1]]);
+        if extension_type == u16::from(ExtensionType::Uninitialized) {
+            break;
+        }
+        let length = usize::from(u16::from_le_bytes([rest[2], rest[3]]));
+        let Some(value) = rest.get(4..4 + length) else {
+            return Err(format!(
+                "malformed extension data: extension {} overruns the account",
+                extension_type
+            ));
+        };
+        entries.push((extension_type, value));
+        rest = &rest[4 + length..];
+    }
+
+    Ok(entries)
+}
+
+/// The value of the `extension_type` entry among `entries`, if present.
+fn extension_value<'a>(
+    entries: &[(u16, &'a [u8])],
+    extension_type: ExtensionType,
+) -> Option<&'a [u8]> {
+    entries
+        .iter()
+        .find(|&&(entry_type, _)| entry_type == u16::from(extension_type))
+        .map(|&(_, value)| value)
+}
+
+/// The extension types among `entries` known to `spl-token-2022`.
+fn known_extension_types(entries: &[(u16, &[u8])]) -> Vec<ExtensionType> {
+    entries
+        .iter()
+        .filter_map(|&(extension_type, _)| ExtensionType::try_from(extension_type).ok())
+        .collect()
+}
+
+/// Reads the `OptionalNonZeroPubkey` extension field at `offset`, which is
+/// all zeroes when unset.
+pub(crate) fn optional_pubkey(value: &[u8], offset: usize) -> Option<Pubkey> {
+    let bytes: [u8; PUBKEY_BYTES] = value.get(offset..offset + PUBKEY_BYTES)?.try_into().ok()?;
+    let pubkey = Pubkey::new_from_array(bytes);
+    (pubkey != Pubkey::default()).then_some(pubkey)
+}
+
+/// `getTokenAccountsByOwner` with base64 encoding.
+///
+/// The typed helper on `RpcClient` always requests `jsonParsed` data, which
+/// cannot be unpacked into token account state.
+async fn get_token_accounts_by_owner(
+    rpc_client: &RpcClient,
+    owner: &Pubkey,
+    filter: TokenAccountsFilter,
+) -> Result<Vec<RpcKeyedAccount>> {
+    let filter = match filter {
+        TokenAccountsFilter::Mint(mint) => json!({ "mint": mint.to_string() }),
+        TokenAccountsFilter::ProgramId(program_id) => {
+            json!({ "programId": program_id.to_string() })
+        }
+    };
+
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(rpc_client.commitment()),
+        data_slice: None,
+        min_context_slot: None,
+    };
+
+    let response: Response<Vec<RpcKeyedAccount>> = rpc_client
+        .send(
+            RpcRequest::GetTokenAccountsByOwner,
+            json!([owner.to_string(), filter, config]),
+        )
+        .await?;
+
+    Ok(response.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_token_2022::{
+        extension::{
+            confidential_transfer::EncryptedBalance, mint_close_authority::MintCloseAuthority,
+            non_transferable::NonTransferable, BaseStateWithExtensionsMut, StateWithExtensionsMut,
+        },
+        state::AccountState,
+    };
+
+    /// Token-2022 account data holding `entries` after the base state.
+    fn token_account_data(entries: &[(u16, &[u8])]) -> Vec<u8> {
+        let account = TokenAccount {
+            mint: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        };
+        let mut data = vec![0; TokenAccount::LEN];
+        account.pack_into_slice(&mut data);
+        data.push(u8::from(AccountType::Account));
+        for (extension_type, value) in entries {
+            data.extend(extension_type.to_le_bytes());
+            data.extend((value.len() as u16).to_le_bytes());
+            data.extend(*value);
+        }
+        data
+    }
+
+    #[test]
+    fn reads_withheld_fees_after_unknown_extension() {
+        // PausableAccount (27) is newer than the bundled spl-token-2022
+        let data = token_account_data(&[(27, &[]), (2, &42u64.to_le_bytes())]);
+
+        let (_, extensions, withheld_amount, _) = unpack_token_account(&data).unwrap();
+        assert_eq!(extensions, vec![ExtensionType::TransferFeeAmount]);
+        assert_eq!(withheld_amount, 42);
+    }
+
+    #[test]
+    fn reads_confidential_balance_after_unknown_extension() {
+        let extension = ConfidentialTransferAccount {
+            pending_balance_credit_counter: 1u64.into(),
+            available_balance: bytemuck::cast::<[u8; 64], EncryptedBalance>([1; 64]),
+            ..ConfidentialTransferAccount::default()
+        };
+        let data = token_account_data(&[(27, &[]), (5, bytemuck::bytes_of(&extension))]);
+
+        let (_, _, _, confidential) = unpack_token_account(&data).unwrap();
+        assert!(matches!(
+            confidential,
+            Some(ConfidentialBalance::Unchecked(decoded)) if *decoded == extension
+        ));
+    }
+
+    #[test]
+    fn blocks_malformed_confidential_balance() {
+        let data = token_account_data(&[(5, &[0; 10])]);
+
+        let (_, _, _, confidential) = unpack_token_account(&data).unwrap();
+        assert!(matches!(
+            confidential,
+            Some(ConfidentialBalance::Blocked(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_withheld_fees() {
+        let data = token_account_data(&[(2, &[1, 2, 3])]);
+
+        let error = unpack_token_account(&data).unwrap_err();
+        assert!(error.contains("truncated transfer fee amount"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_overrunning_extension() {
+        let mut data = token_account_data(&[(2, &42u64.to_le_bytes())]);
+        data.truncate(data.len() - 1);
+
+        let error = unpack_token_account(&data).unwrap_err();
+        assert!(error.contains("overruns the account"), "{}", error);
+    }
+
+    /// A Token-2022 mint packed with `extensions` initialized in order, whose
+    /// `MintCloseAuthority`, if any, is `close_authority`.
+    fn mint_account(extensions: &[ExtensionType], close_authority: &Pubkey) -> Account {
+        let len = ExtensionType::try_calculate_account_len::<Mint>(extensions).unwrap();
+        let mut data = vec![0; len];
+        let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        for extension_type in extensions {
+            match extension_type {
+                ExtensionType::MintCloseAuthority => {
+                    state
+                        .init_extension::<MintCloseAuthority>(true)
+                        .unwrap()
+                        .close_authority = Some(*close_authority).try_into().unwrap();
+                }
+                ExtensionType::NonTransferable => {
+                    state.init_extension::<NonTransferable>(true).unwrap();
+                }
+                _ => unimplemented!("{:?}", extension_type),
+            }
+        }
+        state.base = Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Mint::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+
+        Account {
+            lamports: 1_461_600,
+            data,
+            owner: spl_token_2022::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn closable_mint_filter_matches_close_authority() {
+        let owner = Pubkey::new_unique();
+        let mint = mint_account(&[ExtensionType::MintCloseAuthority], &owner);
+
+        assert!(closable_mint_filter(&owner).bytes_match(&mint.data));
+        let mint_info = decode_mint(&Pubkey::new_unique(), &mint).unwrap();
+        assert_eq!(mint_info.close_authority, Some(owner));
+    }
+
+    #[test]
+    fn closable_mint_filter_skips_other_mints() {
+        let owner = Pubkey::new_unique();
+        let filter = closable_mint_filter(&owner);
+
+        let foreign = mint_account(&[ExtensionType::MintCloseAuthority], &Pubkey::new_unique());
+        assert!(!filter.bytes_match(&foreign.data));
+        let without = mint_account(&[ExtensionType::NonTransferable], &owner);
+        assert!(!filter.bytes_match(&without.data));
+        // Found through the wallet's token accounts instead
+        let later = mint_account(
+            &[
+                ExtensionType::NonTransferable,
+                ExtensionType::MintCloseAuthority,
+            ],
+            &owner,
+        );
+        assert!(!filter.bytes_match(&later.data));
+        assert_eq!(
+            decode_mint(&Pubkey::new_unique(), &later)
+                .unwrap()
+                .close_authority,
+            Some(owner)
+        );
+    }
+}
@@ -0,0 +1,465 @@
+use crate::{
+    batcher::{memo_instruction, with_compute_budget},
+    plan::CleanupPlan,
+};
+use anyhow::{bail, Context, Result};
+use log::{error, info, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+};
+use thiserror::Error;
+
+/// Transaction settings applied to every batch.
+#[derive(Debug, Clone)]
+pub struct ExecutorConfig {
+    /// Compute unit price in micro-lamports
+    pub compute_unit_price: u64,
+    /// Compute unit limit
+    pub compute_unit_limit: u32,
+}
+
+impl Default for ExecutorConfig {
+    fn default() -> Self {
+        Self {
+            compute_unit_price: 220_000,
+            compute_unit_limit: 350_000,
+        }
+    }
+}
+
+/// What happened to a single planned account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountOutcome {
+    /// The account's instructions were confirmed in this transaction.
+    Confirmed { signature: Signature },
+    /// The account's instructions failed, either in isolation or as part of a
+    /// batch that could not be split further.
+    Failed { error: String },
+}
+
+/// The outcome of one planned account.
+#[derive(Debug, Clone)]
+pub struct AccountResult {
+    /// Address of the token account, or of the mint.
+    pub address: Pubkey,
+    /// What happened to it.
+    pub outcome: AccountOutcome,
+}
+
+/// Outcome of executing a [`CleanupPlan`].
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionReport {
+    /// Signatures of the confirmed transactions, one per successful batch.
+    pub signatures: Vec<Signature>,
+    /// Per-account outcomes for every account and mint with instructions, in
+    /// batch order.
+    pub accounts: Vec<AccountResult>,
+}
+
+impl ExecutionReport {
+    /// Number of accounts whose instructions were confirmed.
+    pub fn confirmed_count(&self) -> usize {
+        self.accounts
+            .iter()
+            .filter(|result| matches!(result.outcome, AccountOutcome::Confirmed { .. }))
+            .count()
+    }
+
+    /// Number of accounts that failed.
+    pub fn failed_count(&self) -> usize {
+        self.accounts.len() - self.confirmed_count()
+    }
+}
+
+/// Signs and sends every batch of `plan`.
+///
+/// Each account's instructions travel in a single transaction, so every
+/// account either fully succeeds or is left untouched. A batch that fails
+/// simulation does not stop the run: it is split in half and retried until
+/// the accounts causing the failure are isolated and marked as failed, while
+/// the rest are still cleaned up.
+pub async fn execute_plan(
+    rpc_client: &RpcClient,
+    keypair: &Keypair,
+    plan: &CleanupPlan,
+    config: &ExecutorConfig,
+) -> Result<ExecutionReport> {
+    if plan.owner != keypair.pubkey() {
+        bail!(
+            "Plan was built for wallet {} but signer is {}",
+            plan.owner,
+            keypair.pubkey()
+        );
+    }
+
+    if plan.is_empty() {
+        info!("No token accounts to process");
+        return Ok(ExecutionReport::default());
+    }
+
+    let memo = plan.memo.as_deref();
+    let report = execute_batches(plan, |instructions| async move {
+        process_instruction_batch(
+            rpc_client,
+            keypair,
+            &instructions,
+            memo,
+            config.compute_unit_price,
+            config.compute_unit_limit,
+        )
+        .await
+    })
+    .await;
+
+    Ok(report)
+}
+
+/// Runs every batch of `plan` through `send`, which signs and sends the
+/// instructions of one transaction, bisecting batches that fail simulation.
+async fn execute_batches<F, Fut>(plan: &CleanupPlan, mut send: F) -> ExecutionReport
+where
+    F: FnMut(Vec<Instruction>) -> Fut,
+    Fut: Future<Output = Result<Signature, BatchError>>,
+{
+    let mut report = ExecutionReport::default();
+
+    info!(
+        "Processing {} instructions for {} accounts and {} mints",
+        plan.instruction_count(),
+        plan.accounts
+            .iter()
+            .filter(|account| account.closes())
+            .count(),
+        plan.mints.iter().filter(|mint| mint.closes()).count()
+    );
+
+    let planned_instructions: HashMap<_, _> = plan
+        .accounts
+        .iter()
+        .map(|account| (account.address, &account.instructions))
+        .chain(
+            plan.mints
+                .iter()
+                .map(|mint| (mint.address, &mint.instructions)),
+        )
+        .collect();
+
+    for (index, batch) in plan.batches.iter().enumerate() {
+        info!(
+            "Processing batch {} of {}: {} accounts, {} instructions",
+            index + 1,
+            plan.batches.len(),
+            batch.accounts.len(),
+            batch.instructions.len()
+        );
+
+        let mut pending = VecDeque::from([batch.accounts.clone()]);
+        while let Some(accounts) = pending.pop_front() {
+            let instructions: Vec<_> = accounts
+                .iter()
+                .flat_map(|address| planned_instructions[address].iter().cloned())
+                .collect();
+
+            match send(instructions).await {
+                Ok(signature) => {
+                    report.signatures.push(signature);
+                    record(
+                        &mut report,
+                        &accounts,
+                        AccountOutcome::Confirmed { signature },
+                    );
+                }
+                Err(BatchError::Simulation(reason)) if accounts.len() > 1 => {
+                    // Bisect to find the account(s) responsible
+                    warn!(
+                        "Batch of {} accounts failed simulation ({}); splitting",
+                        accounts.len(),
+                        reason
+                    );
+                    let (first, second) = accounts.split_at(accounts.len() / 2);
+                    pending.push_front(second.to_vec());
+                    pending.push_front(first.to_vec());
+                }
+                Err(e) => {
+                    for address in &accounts {
+                        error!("Account {} failed: {}", address, e);
+                    }
+                    record(
+                        &mut report,
+                        &accounts,
+                        AccountOutcome::Failed {
+                            error: e.to_string(),
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    report
+}
+
+fn record(report: &mut ExecutionReport, accounts: &[Pubkey], outcome: AccountOutcome) {
+    report
+        .accounts
+        .extend(accounts.iter().map(|address| AccountResult {
+            address: *address,
+            outcome: outcome.clone(),
+        }));
+}
+
+/// Why a batch could not be confirmed.
+#[derive(Debug, Error)]
+enum BatchError {
+    /// Simulation rejected the transaction, which is deterministic for the
+    /// accounts involved and safe to retry with fewer of them.
+    #[error("Transaction simulation failed: {0}")]
+    Simulation(String),
+    /// Any other failure, such as an RPC error or a confirmation timeout. The
+    /// transaction may still have landed, so it is not retried.
+    #[error("{0:#}")]
+    Other(#[from] anyhow::Error),
+}
+
+async fn process_instruction_batch(
+    rpc_client: &RpcClient,
+    keypair: &Keypair,
+    instructions: &[Instruction],
+    memo: Option<&str>,
+    compute_unit_price: u64,
+    compute_unit_limit: u32,
+) -> Result<Signature, BatchError> {
+    // Add compute budget instructions ahead of the actual instructions, and
+    // the memo after them
+    let mut transaction_instructions =
+        with_compute_budget(instructions, compute_unit_price, compute_unit_limit);
+    transaction_instructions.extend(memo.map(memo_instruction));
+
+    // Create and send transaction
+    let recent_blockhash = rpc_client
+        .get_latest_blockhash()
+        .await
+        .context("Failed to get recent blockhash")?;
+
+    let mut transaction =
+        Transaction::new_with_payer(&transaction_instructions, Some(&keypair.pubkey()));
+
+    transaction.sign(&[keypair], recent_blockhash);
+
+    // Simulate transaction first
+    match rpc_client.simulate_transaction(&transaction).await {
+        Ok(simulation_result) => {
+            if let Some(err) = simulation_result.value.err {
+                let reason = describe_simulation_error(
+                    &err,
+                    simulation_result.value.logs.as_deref().unwrap_or_default(),
+                );
+                error!("Transaction simulation failed: {}", reason);
+                return Err(BatchError::Simulation(reason));
+            }
+            info!("Transaction simulation successful");
+        }
+        Err(e) => {
+            warn!("Failed to simulate transaction: {:?}", e);
+        }
+    }
+
+    // Send and confirm transaction
+    let signature = rpc_client
+        .send_and_confirm_transaction(&transaction)
+        .await
+        .context("Failed to send and confirm transaction")?;
+
+    info!("Transaction successful! Signature: {}", signature);
+    info!("View on Solscan: https://solscan.io/tx/{}", signature);
+
+    Ok(signature)
+}
+
+/// Describes a simulation failure, preferring the program's own error message
+/// (for example "Error: Account is frozen") over the bare error code.
+fn describe_simulation_error(err: &TransactionError, logs: &[String]) -> String {
+    let program_error = logs
+        .iter()
+        .rev()
+        .find_map(|line| line.strip_prefix("Program log: Error: "));
+
+    match program_error {
+        Some(message) => format!("{} ({})", message, err),
+        None => err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        batcher::BatchLimits,
+        discovery::DiscoveredAccounts,
+        planner::{plan_cleanup, PlannerConfig},
+        test_support::token_account,
+    };
+    use solana_sdk::instruction::InstructionError;
+
+    /// Plans `count` empty accounts of one wallet, all in a single batch.
+    fn plan(count: usize) -> CleanupPlan {
+        let owner = Pubkey::new_unique();
+        let discovered = DiscoveredAccounts {
+            token_accounts: (0..count)
+                .map(|_| token_account().owner(&owner).build())
+                .collect(),
+            ..DiscoveredAccounts::default()
+        };
+        let plan = plan_cleanup(&owner, discovered, &PlannerConfig::default()).unwrap();
+        assert_eq!(plan.batches.len(), 1);
+        plan
+    }
+
+    fn touches(instructions: &[Instruction], address: &Pubkey) -> bool {
+        instructions.iter().any(|instruction| {
+            instruction
+                .accounts
+                .iter()
+                .any(|meta| meta.pubkey == *address)
+        })
+    }
+
+    #[tokio::test]
+    async fn isolates_account_failing_simulation() {
+        let plan = plan(5);
+        let addresses: Vec<_> = plan
+            .accounts
+            .iter()
+            .map(|account| account.address)
+            .collect();
+        let bad = addresses[3];
+        let mut sent = Vec::new();
+
+        let report = execute_batches(&plan, |instructions| {
+            let fails = touches(&instructions, &bad);
+            sent.push(instructions.len());
+            async move {
+                if fails {
+                    Err(BatchError::Simulation("Account is frozen".to_string()))
+                } else {
+                    Ok(Signature::new_unique())
+                }
+            }
+        })
+        .await;
+
+        // 5 fails, then [0, 1] lands, [2, 3, 4] fails, [2] lands, [3, 4]
+        // fails, [3] fails and [4] lands
+        assert_eq!(sent, vec![5, 2, 3, 1, 2, 1, 1]);
+        assert_eq!(
+            report
+                .accounts
+                .iter()
+                .map(|result| result.address)
+                .collect::<Vec<_>>(),
+            addresses
+        );
+        for result in &report.accounts {
+            if result.address == bad {
+                assert_eq!(
+                    result.outcome,
+                    AccountOutcome::Failed {
+                        error: "Transaction simulation failed: Account is frozen".to_string()
+                    }
+                );
+            } else {
+                assert!(matches!(result.outcome, AccountOutcome::Confirmed { .. }));
+            }
+        }
+        assert_eq!(report.signatures.len(), 3);
+        assert_eq!(report.confirmed_count(), 4);
+        assert_eq!(report.failed_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_split_batch_on_other_errors() {
+        let plan = plan(4);
+        let mut sends = 0;
+
+        let report = execute_batches(&plan, |_| {
+            sends += 1;
+            async { Err(BatchError::Other(anyhow::anyhow!("confirmation timed out"))) }
+        })
+        .await;
+
+        // The transaction may have landed, so it is never retried
+        assert_eq!(sends, 1);
+        assert_eq!(report.failed_count(), 4);
+        assert!(report.signatures.is_empty());
+    }
+
+    #[tokio::test]
+    async fn sends_each_batch() {
+        let plan = plan(4);
+        let limits = BatchLimits {
+            max_instructions: Some(1),
+            ..BatchLimits::default()
+        };
+        let plan = crate::planner::apply_selection(
+            plan.clone(),
+            &plan
+                .accounts
+                .iter()
+                .map(|account| account.address)
+                .collect(),
+            &limits,
+        )
+        .unwrap();
+
+        let report = execute_batches(&plan, |_| async { Ok(Signature::new_unique()) }).await;
+
+        assert_eq!(report.signatures.len(), 4);
+        assert_eq!(report.confirmed_count(), 4);
+    }
+
+    #[test]
+    fn describes_custom_program_errors() {
+        let err = TransactionError::InstructionError(2, InstructionError::Custom(17));
+
+        assert_eq!(
+            describe_simulation_error(&err, &[]),
+            "Error processing Instruction 2: custom program error: 0x11"
+        );
+    }
+
+    #[test]
+    fn describes_program_log_errors() {
+        let err = TransactionError::InstructionError(2, InstructionError::Custom(17));
+        let logs = [
+            "Program ComputeBudget111111111111111111111111111111 success".to_string(),
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [1]".to_string(),
+            "Program log: Instruction: Burn".to_string(),
+            "Program log: Error: Account is frozen".to_string(),
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA failed: custom program error: 0x11"
+                .to_string(),
+        ];
+
+        assert_eq!(
+            describe_simulation_error(&err, &logs),
+            "Account is frozen (Error processing Instruction 2: custom program error: 0x11)"
+        );
+    }
+
+    #[test]
+    fn describes_errors_outside_instructions() {
+        let logs = ["Program log: Instruction: CloseAccount".to_string()];
+
+        assert_eq!(
+            describe_simulation_error(&TransactionError::InsufficientFundsForFee, &logs),
+            TransactionError::InsufficientFundsForFee.to_string()
+        );
+    }
+}
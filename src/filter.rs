@@ -0,0 +1,761 @@
+use crate::discovery::TokenAccountInfo;
+use anyhow::{bail, Context, Result};
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::amount_to_ui_amount;
+use std::{cmp::Ordering, fmt, fs, path::PathBuf, str::FromStr};
+
+/// An expression selecting the token accounts a run may touch, such as
+/// `amount == 0 || (decimals == 0 && mint !in @protected.txt)`.
+///
+/// Expressions combine comparisons with `&&`, `||`, `!` and parentheses.
+/// A comparison is `field op value` with `==`, `!=`, `<`, `<=`, `>` or `>=`
+/// (ordering only for numbers), or `field in [a, b]` / `field !in @file`,
+/// where the file lists one value per line and `#` starts a comment. The
+/// boolean fields can also be used on their own.
+///
+/// | Field | Type |
+/// | --- | --- |
+/// | `address`, `mint` | address |
+/// | `program` | `spl-token` or `token-2022` |
+/// | `amount`, `lamports` | raw integer |
+/// | `decimals` | integer, unknown if the mint could not be fetched |
+/// | `ui_amount` | number, unknown without decimals |
+/// | `usd_value` | number, unknown without a price |
+/// | `nft`, `frozen`, `native`, `delegated` | boolean |
+///
+/// Any comparison involving an unknown value is false, so negating one, as in
+/// `!(decimals == 0)`, selects accounts whose value is unknown.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    source: String,
+    expr: Expr,
+}
+
+impl Filter {
+    /// Parses `source`, reading any `@file` lists it references.
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source).with_context(|| format!("Invalid filter: {}", source))?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let expr = parser
+            .parse_filter()
+            .with_context(|| format!("Invalid filter: {}", source))?;
+
+        Ok(Self {
+            source: source.to_string(),
+            expr,
+        })
+    }
+
+    /// Returns `true` if `token_account` is selected by the filter.
+    pub fn matches(&self, token_account: &TokenAccountInfo) -> bool {
+        self.expr.evaluate(token_account)
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+/// A token account field that filters can refer to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Address,
+    Mint,
+    Program,
+    Amount,
+    Decimals,
+    UiAmount,
+    UsdValue,
+    Lamports,
+    Nft,
+    Frozen,
+    Native,
+    Delegated,
+}
+
+/// The kind of values a field holds, used to check literals while parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldType {
+    Address,
+    Text,
+    Number,
+    Bool,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "address" => Field::Address,
+            "mint" => Field::Mint,
+            "program" => Field::Program,
+            "amount" => Field::Amount,
+            "decimals" => Field::Decimals,
+            "ui_amount" => Field::UiAmount,
+            "usd_value" => Field::UsdValue,
+            "lamports" => Field::Lamports,
+            "nft" => Field::Nft,
+            "frozen" => Field::Frozen,
+            "native" => Field::Native,
+            "delegated" => Field::Delegated,
+            _ => bail!(
+                "unknown field `{}` (expected address, mint, program, amount, decimals, \
+                 ui_amount, usd_value, lamports, nft, frozen, native or delegated)",
+                name
+            ),
+        })
+    }
+
+    fn field_type(&self) -> FieldType {
+        match self {
+            Field::Address | Field::Mint => FieldType::Address,
+            Field::Program => FieldType::Text,
+            Field::Amount
+            | Field::Decimals
+            | Field::UiAmount
+            | Field::UsdValue
+            | Field::Lamports => FieldType::Number,
+            Field::Nft | Field::Frozen | Field::Native | Field::Delegated => FieldType::Bool,
+        }
+    }
+
+    /// The field's value for `token_account`, or `None` if it is unknown.
+    fn value(&self, token_account: &TokenAccountInfo) -> Option<Value> {
+        let account = &token_account.account;
+        let decimals = token_account
+            .mint_info
+            .as_ref()
+            .map(|mint_info| mint_info.mint.decimals);
+        let ui_amount = decimals.map(|decimals| amount_to_ui_amount(account.amount, decimals));
+
+        Some(match self {
+            Field::Address => Value::Address(token_account.address),
+            Field::Mint => Value::Address(account.mint),
+            Field::Program => Value::Text(if token_account.program_id == spl_token_2022::id() {
+                "token-2022".to_string()
+            } else {
+                "spl-token".to_string()
+            }),
+            Field::Amount => Value::Integer(account.amount),
+            Field::Decimals => Value::Integer(u64::from(decimals?)),
+            Field::UiAmount => Value::Decimal(ui_amount?),
+            Field::UsdValue => Value::Decimal(ui_amount? * token_account.usd_price?),
+            Field::Lamports => Value::Integer(token_account.lamports),
+            Field::Nft => Value::Bool(token_account.is_nft()),
+            Field::Frozen => Value::Bool(account.is_frozen()),
+            Field::Native => Value::Bool(account.is_native()),
+            Field::Delegated => Value::Bool(account.delegate.is_some()),
+        })
+    }
+}
+
+/// A field value or literal.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Address(Pubkey),
+    Text(String),
+    Integer(u64),
+    Decimal(f64),
+    Bool(bool),
+}
+
+impl Value {
+    /// Parses `literal` as a value of `field_type`.
+    fn parse(field_type: FieldType, literal: &str) -> Result<Self> {
+        Ok(match field_type {
+            FieldType::Address => Value::Address(
+                Pubkey::from_str(literal)
+                    .map_err(|_| anyhow::anyhow!("`{}` is not a valid address", literal))?,
+            ),
+            FieldType::Text => Value::Text(literal.to_string()),
+            FieldType::Number => match literal.parse::<u64>() {
+                Ok(integer) => Value::Integer(integer),
+                Err(_) => Value::Decimal(
+                    literal
+                        .parse::<f64>()
+                        .map_err(|_| anyhow::anyhow!("`{}` is not a number", literal))?,
+                ),
+            },
+            FieldType::Bool => match literal {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => bail!("`{}` is not true or false", literal),
+            },
+        })
+    }
+
+    /// Orders numbers, and reports other values as equal or incomparable.
+    fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+            (Value::Integer(a), Value::Decimal(b)) => (*a as f64).partial_cmp(b),
+            (Value::Decimal(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)),
+            (Value::Decimal(a), Value::Decimal(b)) => a.partial_cmp(b),
+            // Other values are only ever compared for equality
+            (a, b) => (a == b).then_some(Ordering::Equal),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Flag(Field),
+    Compare(Field, Operator, Value),
+    In {
+        field: Field,
+        values: Vec<Value>,
+        negated: bool,
+    },
+}
+
+impl Expr {
+    fn evaluate(&self, token_account: &TokenAccountInfo) -> bool {
+        match self {
+            Expr::Or(left, right) => left.evaluate(token_account) || right.evaluate(token_account),
+            Expr::And(left, right) => left.evaluate(token_account) && right.evaluate(token_account),
+            Expr::Not(expr) => !expr.evaluate(token_account),
+            Expr::Flag(field) => field.value(token_account) == Some(Value::Bool(true)),
+            Expr::Compare(field, operator, literal) => {
+                // Unknown values never match
+                let Some(value) = field.value(token_account) else {
+                    return false;
+                };
+                let ordering = value.compare(literal);
+                match operator {
+                    Operator::Eq => ordering == Some(Ordering::Equal),
+                    Operator::Ne => ordering != Some(Ordering::Equal),
+                    Operator::Lt => ordering == Some(Ordering::Less),
+                    Operator::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                    Operator::Gt => ordering == Some(Ordering::Greater),
+                    Operator::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+                }
+            }
+            Expr::In {
+                field,
+                values,
+                negated,
+            } => {
+                let Some(value) = field.value(token_account) else {
+                    return false;
+                };
+                let found = values
+                    .iter()
+                    .any(|literal| value.compare(literal) == Some(Ordering::Equal));
+                found != *negated
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    And,
+    Or,
+    Not,
+    In,
+    NotIn,
+    Compare(Operator),
+    Word(String),
+    Text(String),
+    File(PathBuf),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::LeftParen => write!(f, "`(`"),
+            Token::RightParen => write!(f, "`)`"),
+            Token::LeftBracket => write!(f, "`[`"),
+            Token::RightBracket => write!(f, "`]`"),
+            Token::Comma => write!(f, "`,`"),
+            Token::And => write!(f, "`&&`"),
+            Token::Or => write!(f, "`||`"),
+            Token::Not => write!(f, "`!`"),
+            Token::In => write!(f, "`in`"),
+            Token::NotIn => write!(f, "`!in`"),
+            Token::Compare(_) => write!(f, "comparison"),
+            Token::Word(word) => write!(f, "`{}`", word),
+            Token::Text(text) => write!(f, "\"{}\"", text),
+            Token::File(path) => write!(f, "`@{}`", path.display()),
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, len) = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => (Token::LeftParen, 1),
+            ')' => (Token::RightParen, 1),
+            '[' => (Token::LeftBracket, 1),
+            ']' => (Token::RightBracket, 1),
+            ',' => (Token::Comma, 1),
+            '&' if next == Some('&') => (Token::And, 2),
+            '|' if next == Some('|') => (Token::Or, 2),
+            '=' if next == Some('=') => (Token::Compare(Operator::Eq), 2),
+            '!' if next == Some('=') => (Token::Compare(Operator::Ne), 2),
+            '<' if next == Some('=') => (Token::Compare(Operator::Le), 2),
+            '>' if next == Some('=') => (Token::Compare(Operator::Ge), 2),
+            '<' => (Token::Compare(Operator::Lt), 1),
+            '>' => (Token::Compare(Operator::Gt), 1),
+            '!' if chars[i + 1..].starts_with(&['i', 'n'])
+                && !chars.get(i + 3).copied().is_some_and(is_word_char) =>
+            {
+                (Token::NotIn, 3)
+            }
+            '!' => (Token::Not, 1),
+            '"' => {
+                let Some(end) = chars[i + 1..].iter().position(|&c| c == '"') else {
+                    bail!("unterminated string");
+                };
+                let text: String = chars[i + 1..i + 1 + end].iter().collect();
+                (Token::Text(text), end + 2)
+            }
+            '@' => {
+                let len = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c.is_whitespace() || matches!(c, ')' | ']' | ','))
+                    .unwrap_or(chars.len() - i - 1);
+                if len == 0 {
+                    bail!("expected a file path after `@`");
+                }
+                let path: String = chars[i + 1..i + 1 + len].iter().collect();
+                (Token::File(PathBuf::from(path)), len + 1)
+            }
+            c if is_word_char(c) => {
+                let len = chars[i..]
+                    .iter()
+                    .position(|&c| !is_word_char(c))
+                    .unwrap_or(chars.len() - i);
+                let word: String = chars[i..i + len].iter().collect();
+                let token = if word == "in" {
+                    Token::In
+                } else {
+                    Token::Word(word)
+                };
+                (token, len)
+            }
+            c => bail!("unexpected character `{}`", c),
+        };
+
+        tokens.push(token);
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser. `&&` binds tighter than `||`, and `!` tighter
+/// than both.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .context("unexpected end of filter")?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        let token = self.next()?;
+        if token != expected {
+            bail!("expected {} but found {}", expected, token);
+        }
+        Ok(())
+    }
+
+    fn parse_filter(&mut self) -> Result<Expr> {
+        let expr = self.parse_or()?;
+        if let Some(token) = self.peek() {
+            bail!("unexpected {}", token);
+        }
+        Ok(expr)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if self.peek() == Some(&Token::Not) {
+            self.position += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        let name = match self.next()? {
+            Token::LeftParen => {
+                let expr = self.parse_or()?;
+                self.expect(Token::RightParen)?;
+                return Ok(expr);
+            }
+            Token::Word(name) => name,
+            token => bail!("expected a field but found {}", token),
+        };
+        let field = Field::from_name(&name)?;
+        let field_type = field.field_type();
+
+        match self.peek() {
+            Some(Token::Compare(operator)) => {
+                let operator = *operator;
+                self.position += 1;
+                if field_type != FieldType::Number
+                    && !matches!(operator, Operator::Eq | Operator::Ne)
+                {
+                    bail!("`{}` can only be compared with == or !=", name);
+                }
+                let literal = self.parse_literal(field_type)?;
+                Ok(Expr::Compare(field, operator, literal))
+            }
+            Some(Token::In | Token::NotIn) => {
+                let negated = self.next()? == Token::NotIn;
+                let values = self.parse_list(field_type)?;
+                Ok(Expr::In {
+                    field,
+                    values,
+                    negated,
+                })
+            }
+            _ if field_type == FieldType::Bool => Ok(Expr::Flag(field)),
+            _ => bail!("expected a comparison after `{}`", name),
+        }
+    }
+
+    fn parse_literal(&mut self, field_type: FieldType) -> Result<Value> {
+        match self.next()? {
+            Token::Word(literal) | Token::Text(literal) => Value::parse(field_type, &literal),
+            token => bail!("expected a value but found {}", token),
+        }
+    }
+
+    /// Parses `[a, b, ...]` or `@file`.
+    fn parse_list(&mut self, field_type: FieldType) -> Result<Vec<Value>> {
+        match self.next()? {
+            Token::File(path) => {
+                let contents = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read filter list {}", path.display()))?;
+                contents
+                    .lines()
+                    .map(|line| line.split('#').next().unwrap_or_default().trim())
+                    .filter(|line| !line.is_empty())
+                    .map(|line| {
+                        Value::parse(field_type, line)
+                            .with_context(|| format!("Invalid entry in {}", path.display()))
+                    })
+                    .collect()
+            }
+            Token::LeftBracket => {
+                let mut values = Vec::new();
+                if self.peek() == Some(&Token::RightBracket) {
+                    self.position += 1;
+                    return Ok(values);
+                }
+                loop {
+                    values.push(self.parse_literal(field_type)?);
+                    match self.next()? {
+                        Token::Comma => {}
+                        Token::RightBracket => return Ok(values),
+                        token => bail!("expected `,` or `]` but found {}", token),
+                    }
+                }
+            }
+            token => bail!("expected a list or @file but found {}", token),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use std::path::Path;
+
+    fn token_account(amount: u64, decimals: Option<u8>) -> TokenAccountInfo {
+        let builder = test_support::token_account().amount(amount);
+        match decimals {
+            Some(decimals) => builder.decimals(decimals),
+            None => builder.unknown_mint(),
+        }
+        .build()
+    }
+
+    fn matches(source: &str, token_account: &TokenAccountInfo) -> bool {
+        Filter::parse(source).unwrap().matches(token_account)
+    }
+
+    fn parse_error(source: &str) -> String {
+        format!("{:#}", Filter::parse(source).unwrap_err())
+    }
+
+    /// Writes `contents` to a fresh file in the temporary directory.
+    fn list_file(contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("filter-list-{}.txt", Pubkey::new_unique()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let empty = token_account(0, Some(6));
+
+        // Parsed as `amount == 0 || (amount == 1 && decimals == 9)`
+        assert!(matches(
+            "amount == 0 || amount == 1 && decimals == 9",
+            &empty
+        ));
+        assert!(!matches(
+            "(amount == 0 || amount == 1) && decimals == 9",
+            &empty
+        ));
+        assert!(matches(
+            "decimals == 9 && amount == 1 || amount == 0",
+            &empty
+        ));
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        let empty = token_account(0, Some(6));
+
+        assert!(!matches("!amount == 0 && decimals == 6", &empty));
+        assert!(matches("!(amount == 0 && decimals == 9)", &empty));
+        assert!(matches("!!(amount == 0)", &empty));
+    }
+
+    #[test]
+    fn tokenizes_not_in_and_not() {
+        assert_eq!(
+            tokenize("mint !in [a]").unwrap(),
+            vec![
+                Token::Word("mint".to_string()),
+                Token::NotIn,
+                Token::LeftBracket,
+                Token::Word("a".to_string()),
+                Token::RightBracket,
+            ]
+        );
+        // A field starting with `in` is negated, not an `!in`
+        assert_eq!(
+            tokenize("!index").unwrap(),
+            vec![Token::Not, Token::Word("index".to_string())]
+        );
+        assert_eq!(
+            tokenize("!(nft)").unwrap(),
+            vec![
+                Token::Not,
+                Token::LeftParen,
+                Token::Word("nft".to_string()),
+                Token::RightParen,
+            ]
+        );
+        assert_eq!(tokenize("!").unwrap(), vec![Token::Not]);
+        assert_eq!(
+            tokenize("!i").unwrap(),
+            vec![Token::Not, Token::Word("i".to_string())]
+        );
+    }
+
+    #[test]
+    fn compares_integers_and_decimals() {
+        let account = token_account(1_500_000, Some(6));
+
+        assert!(matches("amount == 1500000", &account));
+        assert!(matches("amount > 1499999.5", &account));
+        assert!(matches("ui_amount == 1.5", &account));
+        assert!(matches("ui_amount > 1", &account));
+        assert!(matches("ui_amount < 2", &account));
+        assert!(matches("ui_amount >= 1.5 && ui_amount <= 1.5", &account));
+        assert!(!matches("ui_amount != 1.5", &account));
+        // Larger than f64 can hold exactly, still compared as integers
+        assert!(!matches(
+            "amount == 18446744073709551615",
+            &token_account(u64::MAX - 1, Some(0))
+        ));
+    }
+
+    #[test]
+    fn unknown_values_never_match() {
+        let unknown_mint = token_account(5, None);
+
+        assert!(!matches("decimals == 0", &unknown_mint));
+        assert!(!matches("decimals != 0", &unknown_mint));
+        assert!(!matches("ui_amount < 1", &unknown_mint));
+        assert!(!matches("usd_value < 1", &token_account(5, Some(0))));
+        assert!(!matches("decimals in [0, 6, 9]", &unknown_mint));
+        assert!(!matches("decimals !in [0, 6, 9]", &unknown_mint));
+        // Negating a comparison on an unknown value selects the account
+        assert!(matches("!(decimals == 0)", &unknown_mint));
+    }
+
+    #[test]
+    fn matches_fields() {
+        let account = token_account(0, Some(0));
+
+        assert!(matches(
+            &format!("address == {}", account.address),
+            &account
+        ));
+        assert!(matches(
+            &format!("mint in [{}]", account.account.mint),
+            &account
+        ));
+        assert!(matches("program == spl-token", &account));
+        assert!(matches("program != \"token-2022\"", &account));
+        assert!(matches("lamports == 2039280", &account));
+        assert!(!matches("nft || frozen || native || delegated", &account));
+        assert!(matches("frozen == false", &account));
+        assert!(!matches("amount in []", &account));
+    }
+
+    #[test]
+    fn reads_file_lists_with_comments() {
+        let account = token_account(0, Some(0));
+        let path = list_file(&format!(
+            "# protected mints\n\n{}  # ours\n   {}\n",
+            Pubkey::new_unique(),
+            account.account.mint
+        ));
+
+        assert!(matches(&format!("mint in @{}", path.display()), &account));
+        assert!(!matches(&format!("mint !in @{}", path.display()), &account));
+        assert!(matches(
+            &format!("(mint in @{}) && amount == 0", path.display()),
+            &account
+        ));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn displays_source() {
+        let source = "amount == 0 || nft";
+        assert_eq!(Filter::parse(source).unwrap().to_string(), source);
+    }
+
+    #[test]
+    fn reports_errors() {
+        let cases = [
+            ("amount == \"0", "unterminated string"),
+            ("mint in @", "expected a file path after `@`"),
+            ("amount = 0", "unexpected character `=`"),
+            ("amount ==", "unexpected end of filter"),
+            ("(amount == 0", "unexpected end of filter"),
+            ("(amount == 0 nft", "expected `)` but found `nft`"),
+            ("amount == 0)", "unexpected `)`"),
+            ("== 0", "expected a field but found comparison"),
+            ("balance == 0", "unknown field `balance`"),
+            ("mint < 1", "`mint` can only be compared with == or !="),
+            ("nft > true", "`nft` can only be compared with == or !="),
+            ("amount", "expected a comparison after `amount`"),
+            ("amount == (", "expected a value but found `(`"),
+            ("amount in [1 2]", "expected `,` or `]` but found `2`"),
+            ("amount in 1", "expected a list or @file but found `1`"),
+            ("mint == abc", "`abc` is not a valid address"),
+            ("amount == many", "`many` is not a number"),
+            ("nft == yes", "`yes` is not true or false"),
+        ];
+        for (source, expected) in cases {
+            let error = parse_error(source);
+            assert!(
+                error.starts_with(&format!("Invalid filter: {}: ", source)),
+                "{}",
+                error
+            );
+            assert!(error.contains(expected), "{}: {}", source, error);
+        }
+    }
+
+    #[test]
+    fn reports_file_list_errors() {
+        let missing = Path::new("/nonexistent/filter-list.txt");
+        let error = parse_error(&format!("mint in @{}", missing.display()));
+        assert!(
+            error.contains("Failed to read filter list /nonexistent/filter-list.txt"),
+            "{}",
+            error
+        );
+
+        let path = list_file("not-an-address\n");
+        let error = parse_error(&format!("mint in @{}", path.display()));
+        assert!(
+            error.contains(&format!("Invalid entry in {}", path.display())),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains("`not-an-address` is not a valid address"),
+            "{}",
+            error
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+//! Burn and close unnecessary SPL Token and Token-2022 accounts to recover
+//! rent-exempt SOL.
+//!
+//! Cleanup is split into three stages so it can be embedded in other services:
+//!
+//! 1. [`discover_token_accounts`] fetches every token account owned by a wallet.
+//! 2. [`plan_cleanup`] decides what to do with each account and produces a
+//!    [`CleanupPlan`] without touching the chain. [`render_plan`] describes it
+//!    for dry runs.
+//! 3. [`execute_plan`] signs and sends the plan's batches.
+
+pub mod activity;
+pub mod batcher;
+pub mod confidential;
+pub mod discovery;
+pub mod executor;
+pub mod filter;
+pub mod metaplex;
+pub mod plan;
+pub mod planner;
+pub mod pricing;
+pub mod protection;
+pub mod registry;
+pub mod report;
+pub mod risk;
+pub mod tui;
+
+#[cfg(test)]
+mod test_support;
+
+pub use activity::{fetch_last_activity, ActivityCache};
+pub use batcher::BatchLimits;
+pub use confidential::{prepare_confidential_accounts, ConfidentialBalance};
+pub use discovery::{
+    discover_closable_mints, discover_token_accounts, fetch_mints, token_program_ids,
+    DiscoveredAccounts, MintInfo, TokenAccountInfo, UnreadableAccount,
+};
+pub use executor::{execute_plan, AccountOutcome, AccountResult, ExecutionReport, ExecutorConfig};
+pub use filter::Filter;
+pub use metaplex::{fetch_metaplex_nfts, MetaplexNft, TokenStandard};
+pub use plan::{AccountAction, AccountPlan, Batch, CleanupPlan, MintAction, MintPlan, SkipReason};
+pub use planner::{apply_selection, nft_burn_candidates, plan_cleanup, NftPolicy, PlannerConfig};
+pub use pricing::{apply_prices, JsonFilePriceSource, JupiterPriceSource, PriceSource};
+pub use protection::{ProtectedKind, ProtectionRule, ProtectionRules, RuleSource};
+pub use registry::{Cluster, KnownMint};
+pub use report::render_plan;
+pub use risk::MintRisk;
+pub use tui::select_accounts;
@@ -1,21 +1,28 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use clap::Parser;
 use log::{error, info, warn};
-use solana_client::rpc_client::RpcClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
-    compute_budget::ComputeBudgetInstruction,
-    instruction::Instruction,
+    native_token::lamports_to_sol,
     pubkey::Pubkey,
     signature::{Keypair, Signer},
-    system_instruction,
-    transaction::Transaction,
+    transaction::MAX_TX_ACCOUNT_LOCKS,
 };
-use spl_token::{
-    instruction::{burn, close_account},
-    state::Account as TokenAccount,
+use solana_token_burn_close::{
+    apply_prices, apply_selection, discover_closable_mints, discover_token_accounts, execute_plan,
+    fetch_last_activity, fetch_metaplex_nfts, nft_burn_candidates, plan_cleanup,
+    prepare_confidential_accounts, pricing::JUPITER_PRICE_API_URL, render_plan, select_accounts,
+    AccountOutcome, ActivityCache, BatchLimits, Cluster, ExecutorConfig, Filter,
+    JsonFilePriceSource, JupiterPriceSource, NftPolicy, PlannerConfig, ProtectionRules,
+    TokenAccountInfo,
+};
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
 };
-use std::str::FromStr;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -28,13 +35,94 @@ struct Args {
     #[arg(long, env = "PRIVATE_KEY")]
     private_key: String,
 
-    /// Skip USDC token accounts
-    #[arg(long, default_value = "true")]
-    skip_usdc: bool,
-
-    /// Maximum instructions per transaction
-    #[arg(long, default_value = "22")]
-    max_instructions: usize,
+    /// JSON or TOML file listing mints and token accounts that must never be
+    /// burned or closed
+    #[arg(long, env = "PROTECT_FILE")]
+    protect_file: Option<PathBuf>,
+
+    /// Do not protect the built-in registry of well-known mints
+    #[arg(long, env = "NO_DEFAULT_PROTECTION")]
+    no_default_protection: bool,
+
+    /// Remove a mint from the built-in protection for this run (repeatable)
+    #[arg(long = "unprotect-mint", value_name = "MINT")]
+    unprotect_mints: Vec<Pubkey>,
+
+    /// Cluster whose well-known mints are protected (mainnet-beta, devnet,
+    /// testnet, unknown); detected from the genesis hash by default
+    #[arg(long, env = "CLUSTER")]
+    cluster: Option<Cluster>,
+
+    /// Only touch accounts matching this filter expression, e.g.
+    /// 'amount == 0 || (decimals == 0 && mint !in @protected.txt)'
+    #[arg(long, env = "FILTER")]
+    filter: Option<Filter>,
+
+    /// Only touch accounts whose most recent transaction is at least this
+    /// many days old
+    #[arg(long, env = "INACTIVE_DAYS", value_name = "DAYS")]
+    inactive_days: Option<u64>,
+
+    /// JSON file caching each account's most recent transaction between runs
+    #[arg(long, env = "ACTIVITY_CACHE", default_value = ".activity-cache.json")]
+    activity_cache: PathBuf,
+
+    /// Only close accounts that already hold no tokens; never burn anything
+    #[arg(long, env = "CLOSE_EMPTY_ONLY")]
+    close_empty_only: bool,
+
+    /// Only burn balances below this many whole tokens (UI amount, resolved
+    /// per mint using its decimals); larger balances are kept
+    #[arg(long, env = "BURN_BELOW", value_name = "UI_AMOUNT")]
+    burn_below: Option<f64>,
+
+    /// Only burn balances worth less than this many US dollars; larger or
+    /// unpriced balances are kept
+    #[arg(long, env = "BURN_BELOW_USD", value_name = "USD")]
+    burn_below_usd: Option<f64>,
+
+    /// Burn balances of mints with a permanent delegate, non-transferable
+    /// tokens or a transfer hook even when above the burn thresholds or
+    /// unpriced, since they are usually scam airdrops
+    #[arg(long, env = "BURN_RISKY")]
+    burn_risky: bool,
+
+    /// JSON file mapping mint addresses to USD prices, used instead of the
+    /// price API
+    #[arg(long, env = "PRICE_FILE")]
+    price_file: Option<PathBuf>,
+
+    /// Jupiter-style price API endpoint
+    #[arg(long, env = "PRICE_API_URL", default_value = JUPITER_PRICE_API_URL)]
+    price_api_url: String,
+
+    /// How to handle NFTs (0 decimals, supply 1): keep, burn, or ask for each
+    /// one before executing
+    #[arg(long, env = "NFT_POLICY", default_value = "keep")]
+    nft_policy: NftPolicy,
+
+    /// Revoke delegates on token accounts that are kept
+    #[arg(long, env = "REVOKE_DELEGATES")]
+    revoke_delegates: bool,
+
+    /// Also close Token-2022 mints whose close authority is the wallet once
+    /// their supply is burned
+    #[arg(long, env = "CLOSE_MINTS")]
+    close_mints: bool,
+
+    /// Thaw frozen accounts when the wallet is the mint's freeze authority
+    /// (frozen accounts are skipped otherwise)
+    #[arg(long, env = "THAW_FROZEN")]
+    thaw_frozen: bool,
+
+    /// Maximum instructions per transaction (by default batches are packed
+    /// by transaction size and account count only)
+    #[arg(long, env = "MAX_INSTRUCTIONS")]
+    max_instructions: Option<usize>,
+
+    /// Maximum distinct accounts referenced per transaction
+    #[arg(long, env = "MAX_ACCOUNTS", default_value_t = MAX_TX_ACCOUNT_LOCKS)]
+    max_accounts: usize,
 
     /// Compute unit price in micro-lamports
     #[arg(long, default_value = "220000")]
@@ -43,13 +131,29 @@ struct Args {
     /// Compute unit limit
     #[arg(long, default_value = "350000")]
     compute_unit_limit: u32,
-}
 
-const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
-const SPL_TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+    /// Attach an SPL Memo with the memo text and a generated run ID to every
+    /// transaction
+    #[arg(long, env = "MEMO")]
+    memo: bool,
+
+    /// Text of the memo, followed by the run ID
+    #[arg(long, env = "MEMO_TEXT", default_value = "solana-token-burn-close")]
+    memo_text: String,
+
+    /// Review the planned accounts in an interactive terminal UI and choose
+    /// which ones to clean up before anything is sent
+    #[arg(long, env = "TUI")]
+    tui: bool,
+
+    /// Print the cleanup plan and exit without signing or sending anything
+    #[arg(long, env = "DRY_RUN")]
+    dry_run: bool,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    dotenv::dotenv().ok();
     env_logger::init();
     
     let args = Args::parse();
@@ -66,201 +170,189 @@ async fn main() -> Result<()> {
     let keypair = parse_private_key(&args.private_key)?;
     info!("Wallet address: {}", keypair.pubkey());
 
-    // Burn and close all token accounts
-    burn_and_close_all_tokens(
-        &rpc_client,
-        &keypair,
-        args.skip_usdc,
-        args.max_instructions,
-        args.compute_unit_price,
-        args.compute_unit_limit,
-    )
-    .await?;
-
-    info!("Token account cleanup completed successfully");
-    Ok(())
-}
-
-fn parse_private_key(private_key_str: &str) -> Result<Keypair> {
-    let private_key_bytes = bs58::decode(private_key_str)
-        .into_vec()
-        .context("Failed to decode base58 private key")?;
-    
-    Keypair::from_bytes(&private_key_bytes)
-        .context("Failed to create keypair from private key")
-}
-
-async fn burn_and_close_all_tokens(
-    rpc_client: &RpcClient,
-    keypair: &Keypair,
-    skip_usdc: bool,
-    max_instructions: usize,
-    compute_unit_price: u64,
-    compute_unit_limit: u32,
-) -> Result<()> {
-    info!("Fetching token accounts for wallet: {}", keypair.pubkey());
-
-    // Get all token accounts owned by the wallet
-    let token_accounts = rpc_client
-        .get_token_accounts_by_owner(
-            &keypair.pubkey(),
-            solana_client::rpc_request::TokenAccountsFilter::ProgramId(
-                Pubkey::from_str(SPL_TOKEN_PROGRAM_ID)?,
-            ),
-        )
-        .context("Failed to fetch token accounts")?;
+    let run_id = new_run_id();
+    info!("Run ID: {}", run_id);
 
-    if token_accounts.is_empty() {
+    let mut discovered = discover_token_accounts(&rpc_client, &keypair.pubkey()).await?;
+    if args.close_mints {
+        discover_closable_mints(&rpc_client, &keypair.pubkey(), &mut discovered).await?;
+    }
+    if discovered.is_empty() && discovered.closable_mints.is_empty() {
         info!("No token accounts found for this wallet");
         return Ok(());
     }
+    info!(
+        "Found {} token accounts ({} unreadable)",
+        discovered.len(),
+        discovered.unreadable.len()
+    );
 
-    info!("Found {} token accounts", token_accounts.len());
+    if let Some(path) = &args.price_file {
+        apply_prices(&mut discovered, &JsonFilePriceSource::load(path)?).await?;
+    } else if args.burn_below_usd.is_some() {
+        apply_prices(
+            &mut discovered,
+            &JupiterPriceSource::new(args.price_api_url.clone()),
+        )
+        .await?;
+    }
 
-    let mut instructions = Vec::new();
-    let mut accounts_processed = 0;
+    if let Some(inactive_days) = args.inactive_days {
+        let mut activity_cache = ActivityCache::load(&args.activity_cache)?;
+        fetch_last_activity(
+            &rpc_client,
+            &mut discovered,
+            &mut activity_cache,
+            inactive_days,
+        )
+        .await?;
+        activity_cache.save()?;
+    }
 
-    for (pubkey, account) in token_accounts {
-        let token_account_data = TokenAccount::unpack(&account.data)
-            .context("Failed to unpack token account data")?;
+    // Encrypted balances can only be checked with keys derived from the wallet
+    prepare_confidential_accounts(&keypair, &mut discovered);
 
-        // Skip USDC if requested
-        if skip_usdc && token_account_data.mint.to_string() == USDC_MINT {
-            info!("Skipping USDC account: {}", pubkey);
-            continue;
-        }
+    // Metadata is only needed when NFTs may be burned
+    if args.nft_policy != NftPolicy::Keep {
+        fetch_metaplex_nfts(&rpc_client, &mut discovered).await?;
+    }
 
-        // Check if account has tokens to burn
-        if token_account_data.amount > 0 {
-            info!(
-                "Burning {} tokens from account: {} (mint: {})",
-                token_account_data.amount, pubkey, token_account_data.mint
-            );
-
-            let burn_instruction = burn(
-                &spl_token::id(),
-                &pubkey,
-                &token_account_data.mint,
-                &keypair.pubkey(),
-                &[],
-                token_account_data.amount,
-            )?;
-
-            instructions.push(burn_instruction);
+    let cluster = match args.cluster {
+        Some(cluster) => cluster,
+        None => Cluster::detect(&rpc_client).await?,
+    };
+    info!("Cluster: {}", cluster);
+
+    let mut protection = if args.no_default_protection {
+        ProtectionRules::empty()
+    } else {
+        ProtectionRules::builtin(cluster)
+    };
+    for mint in &args.unprotect_mints {
+        if !protection.unprotect_mint(mint) {
+            warn!("Mint {} is not protected by default", mint);
         }
-
-        // Always close the account to recover SOL
-        info!("Closing token account: {}", pubkey);
-        let close_instruction = close_account(
-            &spl_token::id(),
-            &pubkey,
-            &keypair.pubkey(),
-            &keypair.pubkey(),
-            &[],
-        )?;
-
-        instructions.push(close_instruction);
-        accounts_processed += 1;
     }
-
-    if instructions.is_empty() {
-        info!("No token accounts to process");
-        return Ok(());
+    if let Some(path) = &args.protect_file {
+        protection.load_file(path)?;
     }
+    info!(
+        "Protecting {} mints and {} token accounts",
+        protection.mint_count(),
+        protection.account_count()
+    );
 
-    info!("Processing {} instructions for {} accounts", instructions.len(), accounts_processed);
-
-    // Process instructions in batches
-    let mut processed_instructions = 0;
-    while processed_instructions < instructions.len() {
-        let end_index = std::cmp::min(
-            processed_instructions + max_instructions,
-            instructions.len(),
-        );
-
-        let batch_instructions = &instructions[processed_instructions..end_index];
-        
-        info!(
-            "Processing batch: instructions {} to {} (total: {})",
-            processed_instructions + 1,
-            end_index,
-            instructions.len()
-        );
+    let mut planner_config = PlannerConfig {
+        protection,
+        filter: args.filter,
+        inactive_days: args.inactive_days,
+        close_empty_only: args.close_empty_only,
+        nft_policy: args.nft_policy,
+        approved_nfts: HashSet::new(),
+        burn_below: args.burn_below,
+        burn_below_usd: args.burn_below_usd,
+        burn_risky: args.burn_risky,
+        thaw_frozen: args.thaw_frozen,
+        revoke_delegates: args.revoke_delegates,
+        memo: args
+            .memo
+            .then(|| format!("{} run:{}", args.memo_text, run_id)),
+        batch_limits: BatchLimits {
+            max_instructions: args.max_instructions,
+            max_accounts: args.max_accounts,
+            ..BatchLimits::default()
+        },
+    };
+
+    // Dry runs never prompt; unapproved NFTs show up as kept
+    if args.nft_policy == NftPolicy::Ask && !args.dry_run {
+        let candidates = nft_burn_candidates(&keypair.pubkey(), &discovered, &planner_config)?;
+        planner_config.approved_nfts = ask_nft_approvals(&candidates)?;
+    }
 
-        process_instruction_batch(
-            rpc_client,
-            keypair,
-            batch_instructions,
-            compute_unit_price,
-            compute_unit_limit,
-        )
-        .await?;
+    let mut plan = plan_cleanup(&keypair.pubkey(), discovered, &planner_config)?;
 
-        processed_instructions = end_index;
+    if args.tui {
+        let Some(selected) = select_accounts(&plan, cluster)? else {
+            info!("Selection cancelled: no transactions were signed or sent");
+            return Ok(());
+        };
+        plan = apply_selection(plan, &selected, &planner_config.batch_limits)?;
     }
 
-    Ok(())
-}
+    if args.dry_run {
+        print!("{}", render_plan(&plan));
+        info!("Dry run: no transactions were signed or sent");
+        return Ok(());
+    }
 
-async fn process_instruction_batch(
-    rpc_client: &RpcClient,
-    keypair: &Keypair,
-    instructions: &[Instruction],
-    compute_unit_price: u64,
-    compute_unit_limit: u32,
-) -> Result<()> {
-    let mut transaction_instructions = Vec::new();
+    let executor_config = ExecutorConfig {
+        compute_unit_price: args.compute_unit_price,
+        compute_unit_limit: args.compute_unit_limit,
+    };
+    let report = execute_plan(&rpc_client, &keypair, &plan, &executor_config).await?;
 
-    // Add compute budget instructions
-    transaction_instructions.push(
-        ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price),
+    for result in &report.accounts {
+        match &result.outcome {
+            AccountOutcome::Confirmed { signature } => {
+                info!("Confirmed {} in {}", result.address, signature)
+            }
+            AccountOutcome::Failed { error } => error!("Failed {}: {}", result.address, error),
+        }
+    }
+    info!(
+        "Completed {} of {} accounts",
+        report.confirmed_count(),
+        report.accounts.len()
     );
-    transaction_instructions.push(
-        ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit),
+    info!(
+        "Planned recovery: {} SOL rent, {} SOL unwrapped",
+        lamports_to_sol(plan.rent_lamports()),
+        lamports_to_sol(plan.unwrapped_lamports())
     );
 
-    // Add the actual instructions
-    transaction_instructions.extend_from_slice(instructions);
+    if report.failed_count() > 0 {
+        bail!("{} accounts failed to clean up", report.failed_count());
+    }
 
-    // Create and send transaction
-    let recent_blockhash = rpc_client
-        .get_latest_blockhash()
-        .context("Failed to get recent blockhash")?;
+    info!("Token account cleanup completed successfully (run {})", run_id);
+    Ok(())
+}
 
-    let mut transaction = Transaction::new_with_payer(
-        &transaction_instructions,
-        Some(&keypair.pubkey()),
-    );
+/// Identifies this run in logs and memos: the start time in Unix seconds and
+/// a random suffix, so runs sort by time and never collide.
+fn new_run_id() -> String {
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |started| started.as_secs());
+    format!("{}-{:08x}", started, rand::random::<u32>())
+}
 
-    transaction.sign(&[keypair], recent_blockhash);
+fn ask_nft_approvals(candidates: &[&TokenAccountInfo]) -> Result<HashSet<Pubkey>> {
+    let mut approved = HashSet::new();
 
-    // Simulate transaction first
-    match rpc_client.simulate_transaction(&transaction) {
-        Ok(simulation_result) => {
-            if let Some(err) = simulation_result.value.err {
-                error!("Transaction simulation failed: {:?}", err);
-                return Err(anyhow::anyhow!("Transaction simulation failed: {:?}", err));
-            }
-            info!("Transaction simulation successful");
-        }
-        Err(e) => {
-            warn!("Failed to simulate transaction: {:?}", e);
+    for token_account in candidates {
+        print!(
+            "Burn NFT {} held in {}? [y/N] ",
+            token_account.account.mint, token_account.address
+        );
+        io::stdout().flush()?;
+
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if matches!(answer.trim(), "y" | "Y" | "yes") {
+            approved.insert(token_account.address);
         }
     }
 
-    // Send and confirm transaction
-    let signature = rpc_client
-        .send_and_confirm_transaction(&transaction)
-        .context("Failed to send and confirm transaction")?;
-
-    info!(
-        "Transaction successful! Signature: {}",
-        signature
-    );
-    info!(
-        "View on Solscan: https://solscan.io/tx/{}",
-        signature
-    );
+    Ok(approved)
+}
 
-    Ok(())
+fn parse_private_key(private_key_str: &str) -> Result<Keypair> {
+    let private_key_bytes = bs58::decode(private_key_str)
+        .into_vec()
+        .context("Failed to decode base58 private key")?;
+    
+    Keypair::from_bytes(&private_key_bytes)
+        .context("Failed to create keypair from private key")
 }
@@ -0,0 +1,706 @@
+use crate::discovery::{DiscoveredAccounts, MAX_MULTIPLE_ACCOUNTS};
+use anyhow::{Context, Result};
+use log::{info, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    account::Account,
+    instruction::{AccountMeta, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+use std::{collections::HashMap, fmt};
+
+/// Metaplex Token Metadata program.
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey =
+    pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// Instruction discriminator of `Burn`, followed by the `BurnV1` variant.
+const BURN_DISCRIMINATOR: u8 = 41;
+const BURN_V1_DISCRIMINATOR: u8 = 0;
+
+/// Leading `Key` byte of Token Metadata accounts.
+const KEY_MASTER_EDITION_V1: u8 = 2;
+const KEY_METADATA_V1: u8 = 4;
+const KEY_MASTER_EDITION_V2: u8 = 6;
+
+/// Token standard recorded in an NFT's metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenStandard {
+    NonFungible,
+    FungibleAsset,
+    Fungible,
+    NonFungibleEdition,
+    ProgrammableNonFungible,
+    ProgrammableNonFungibleEdition,
+}
+
+impl TokenStandard {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(TokenStandard::NonFungible),
+            1 => Some(TokenStandard::FungibleAsset),
+            2 => Some(TokenStandard::Fungible),
+            3 => Some(TokenStandard::NonFungibleEdition),
+            4 => Some(TokenStandard::ProgrammableNonFungible),
+            5 => Some(TokenStandard::ProgrammableNonFungibleEdition),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` for programmable NFTs, whose token accounts stay frozen
+    /// and whose state lives in a token record.
+    pub fn is_programmable(&self) -> bool {
+        matches!(
+            self,
+            TokenStandard::ProgrammableNonFungible | TokenStandard::ProgrammableNonFungibleEdition
+        )
+    }
+}
+
+impl fmt::Display for TokenStandard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenStandard::NonFungible => write!(f, "NFT"),
+            TokenStandard::FungibleAsset => write!(f, "fungible asset"),
+            TokenStandard::Fungible => write!(f, "fungible"),
+            TokenStandard::NonFungibleEdition => write!(f, "NFT edition"),
+            TokenStandard::ProgrammableNonFungible => write!(f, "programmable NFT"),
+            TokenStandard::ProgrammableNonFungibleEdition => {
+                write!(f, "programmable NFT edition")
+            }
+        }
+    }
+}
+
+/// The Token Metadata accounts of an NFT that `BurnV1` closes along with the
+/// token account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaplexNft {
+    /// Name of the NFT, without the padding stored on chain.
+    pub name: String,
+    /// Metadata account of the mint.
+    pub metadata: Pubkey,
+    /// Master edition account of the mint.
+    pub edition: Pubkey,
+    /// Token record of the token account, for programmable NFTs.
+    pub token_record: Option<Pubkey>,
+    /// Metadata of the verified collection the NFT belongs to, whose size is
+    /// decremented by the burn.
+    pub collection_metadata: Option<Pubkey>,
+    /// Token standard of the NFT.
+    pub token_standard: TokenStandard,
+    /// Lamports held by the metadata, edition and token record accounts,
+    /// recovered on top of the token account's rent.
+    pub lamports: u64,
+}
+
+/// Address of the metadata account of `mint`.
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"metadata",
+            TOKEN_METADATA_PROGRAM_ID.as_ref(),
+            mint.as_ref(),
+        ],
+        &TOKEN_METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Address of the master or print edition account of `mint`.
+pub fn edition_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"metadata",
+            TOKEN_METADATA_PROGRAM_ID.as_ref(),
+            mint.as_ref(),
+            b"edition",
+        ],
+        &TOKEN_METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Address of the token record of a programmable NFT held in `token`.
+pub fn token_record_address(mint: &Pubkey, token: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"metadata",
+            TOKEN_METADATA_PROGRAM_ID.as_ref(),
+            mint.as_ref(),
+            b"token_record",
+            token.as_ref(),
+        ],
+        &TOKEN_METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Builds a Token Metadata `BurnV1` instruction that burns the NFT held in
+/// `token` and closes the token account, metadata, edition and token record,
+/// returning their lamports to `authority`.
+pub fn burn_v1(
+    authority: &Pubkey,
+    nft: &MetaplexNft,
+    mint: &Pubkey,
+    token: &Pubkey,
+    token_program_id: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    // Omitted optional accounts are passed as the program id
+    let optional = |address: Option<Pubkey>, writable: bool| match address {
+        Some(address) if writable => AccountMeta::new(address, false),
+        Some(address) => AccountMeta::new_readonly(address, false),
+        None => AccountMeta::new_readonly(TOKEN_METADATA_PROGRAM_ID, false),
+    };
+
+    let accounts = vec![
+        AccountMeta::new(*authority, true),
+        optional(nft.collection_metadata, true),
+        AccountMeta::new(nft.metadata, false),
+        optional(Some(nft.edition), true),
+        AccountMeta::new(*mint, false),
+        AccountMeta::new(*token, false),
+        // Master edition, its mint and token, and the edition marker are only
+        // needed when burning print editions.
+        optional(None, true),
+        optional(None, false),
+        optional(None, false),
+        optional(None, true),
+        optional(nft.token_record, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::instructions::id(), false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+
+    let mut data = vec![BURN_DISCRIMINATOR, BURN_V1_DISCRIMINATOR];
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    Instruction {
+        program_id: TOKEN_METADATA_PROGRAM_ID,
+        accounts,
+        data,
+    }
+}
+
+/// Looks up the Token Metadata accounts of every NFT in `discovered` and
+/// records them on the token accounts that `BurnV1` can burn.
+///
+/// Print editions and NFTs without a master edition keep `metaplex` unset and
+/// are burned and closed through the token program instead.
+pub async fn fetch_metaplex_nfts(
+    rpc_client: &RpcClient,
+    discovered: &mut DiscoveredAccounts,
+) -> Result<()> {
+    let mut addresses = Vec::new();
+    for token_account in &discovered.token_accounts {
+        if token_account.is_nft() && token_account.account.amount > 0 {
+            let mint = token_account.account.mint;
+            addresses.push(metadata_address(&mint));
+            addresses.push(edition_address(&mint));
+            addresses.push(token_record_address(&mint, &token_account.address));
+        }
+    }
+    if addresses.is_empty() {
+        return Ok(());
+    }
+
+    info!(
+        "Fetching Metaplex metadata for {} NFTs",
+        addresses.len() / 3
+    );
+    let mut accounts = HashMap::with_capacity(addresses.len());
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let fetched = rpc_client
+            .get_multiple_accounts(chunk)
+            .await
+            .context("Failed to fetch Metaplex metadata accounts")?;
+        for (address, account) in chunk.iter().zip(fetched) {
+            if let Some(account) =
+                account.filter(|account| account.owner == TOKEN_METADATA_PROGRAM_ID)
+            {
+                accounts.insert(*address, account);
+            }
+        }
+    }
+
+    for token_account in &mut discovered.token_accounts {
+        if !token_account.is_nft() || token_account.account.amount == 0 {
+            continue;
+        }
+        let mint = token_account.account.mint;
+        token_account.metaplex = match metaplex_nft(&accounts, &mint, &token_account.address) {
+            Ok(nft) => Some(nft),
+            Err(reason) => {
+                warn!(
+                    "Not burning NFT {} through Token Metadata: {}",
+                    mint, reason
+                );
+                None
+            }
+        };
+    }
+
+    Ok(())
+}
+
+/// Assembles the [`MetaplexNft`] of `mint` held in `token` from the fetched
+/// Token Metadata accounts, describing why it cannot be burned with `BurnV1`
+/// otherwise.
+fn metaplex_nft(
+    accounts: &HashMap<Pubkey, Account>,
+    mint: &Pubkey,
+    token: &Pubkey,
+) -> std::result::Result<MetaplexNft, String> {
+    let metadata = metadata_address(mint);
+    let edition = edition_address(mint);
+
+    let metadata_account = accounts.get(&metadata).ok_or("no metadata account")?;
+    let parsed = parse_metadata(&metadata_account.data).ok_or("unreadable metadata account")?;
+    if parsed.mint != *mint {
+        return Err("metadata belongs to another mint".to_string());
+    }
+
+    let edition_account = accounts.get(&edition).ok_or("no master edition account")?;
+    match edition_account.data.first() {
+        Some(&KEY_MASTER_EDITION_V1 | &KEY_MASTER_EDITION_V2) => {}
+        _ => return Err("print editions are not supported".to_string()),
+    }
+
+    let token_standard = parsed.token_standard.unwrap_or(TokenStandard::NonFungible);
+    let mut lamports = metadata_account.lamports + edition_account.lamports;
+
+    let token_record = if token_standard.is_programmable() {
+        let token_record = token_record_address(mint, token);
+        let token_record_account = accounts
+            .get(&token_record)
+            .ok_or("programmable NFT has no token record")?;
+        lamports += token_record_account.lamports;
+        Some(token_record)
+    } else {
+        None
+    };
+
+    Ok(MetaplexNft {
+        name: parsed.name,
+        metadata,
+        edition,
+        token_record,
+        collection_metadata: parsed.verified_collection.as_ref().map(metadata_address),
+        token_standard,
+        lamports,
+    })
+}
+
+/// The fields of a metadata account needed to burn the NFT.
+struct ParsedMetadata {
+    mint: Pubkey,
+    name: String,
+    token_standard: Option<TokenStandard>,
+    verified_collection: Option<Pubkey>,
+}
+
+/// Reads the Borsh-encoded `Metadata` account up to its collection. Older
+/// accounts end before the optional trailing fields, which are then `None`.
+fn parse_metadata(data: &[u8]) -> Option<ParsedMetadata> {
+    let mut reader = Reader { data };
+
+    if reader.u8()? != KEY_METADATA_V1 {
+        return None;
+    }
+    reader.pubkey()?; // update authority
+    let mint = reader.pubkey()?;
+    let len = reader.u32()? as usize;
+    let name = String::from_utf8_lossy(reader.bytes(len)?)
+        .trim_end_matches('\0')
+        .to_string();
+    for _ in 0..2 {
+        // symbol and uri
+        let len = reader.u32()? as usize;
+        reader.bytes(len)?;
+    }
+    reader.bytes(2)?; // seller fee basis points
+    if reader.u8()? == 1 {
+        // creators: address, verified and share
+        let len = reader.u32()? as usize;
+        reader.bytes(len.checked_mul(34)?)?;
+    }
+    reader.bytes(2)?; // primary sale happened, is mutable
+
+    let mut parsed = ParsedMetadata {
+        mint,
+        name,
+        token_standard: None,
+        verified_collection: None,
+    };
+
+    // edition nonce
+    match reader.u8() {
+        Some(1) => {
+            reader.u8()?;
+        }
+        Some(_) => {}
+        None => return Some(parsed),
+    }
+    match reader.u8() {
+        Some(1) => parsed.token_standard = Some(TokenStandard::from_u8(reader.u8()?)?),
+        Some(_) => {}
+        None => return Some(parsed),
+    }
+    if reader.u8() == Some(1) {
+        let verified = reader.u8()? == 1;
+        let key = reader.pubkey()?;
+        if verified {
+            parsed.verified_collection = Some(key);
+        }
+    }
+
+    Some(parsed)
+}
+
+/// Minimal cursor over Borsh-encoded account data.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn pubkey(&mut self) -> Option<Pubkey> {
+        self.bytes(32).map(|bytes| Pubkey::try_from(bytes).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshSerialize;
+    use mpl_token_metadata::{
+        accounts::{MasterEdition, Metadata, TokenRecord},
+        instructions::BurnV1Builder,
+        types::{
+            Collection, Creator, Key, ProgrammableConfig, TokenStandard as MetadataTokenStandard,
+        },
+        MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH, MAX_URI_LENGTH,
+    };
+
+    /// Size of metadata accounts created by the Token Metadata program.
+    const METADATA_ACCOUNT_LEN: usize = 679;
+
+    /// Converts to the `Pubkey` of the `solana-program` used by
+    /// `mpl-token-metadata`.
+    fn mpl<T: From<[u8; 32]>>(pubkey: &Pubkey) -> T {
+        T::from(pubkey.to_bytes())
+    }
+
+    /// Pads a string with NULs the way the program stores metadata strings.
+    fn padded(value: &str, len: usize) -> String {
+        format!("{:\0<len$}", value)
+    }
+
+    fn metadata(mint: &Pubkey) -> Metadata {
+        Metadata {
+            key: Key::MetadataV1,
+            update_authority: mpl(&Pubkey::new_unique()),
+            mint: mpl(mint),
+            name: padded("Degen #1234", MAX_NAME_LENGTH),
+            symbol: padded("DGN", MAX_SYMBOL_LENGTH),
+            uri: padded("https://arweave.net/abc", MAX_URI_LENGTH),
+            seller_fee_basis_points: 500,
+            creators: None,
+            primary_sale_happened: true,
+            is_mutable: true,
+            edition_nonce: None,
+            token_standard: None,
+            collection: None,
+            uses: None,
+            collection_details: None,
+            programmable_config: None,
+        }
+    }
+
+    /// Serializes `metadata` into a zero-padded account, as stored on chain.
+    fn account_data(metadata: &Metadata) -> Vec<u8> {
+        let mut data = metadata.try_to_vec().unwrap();
+        assert!(data.len() <= METADATA_ACCOUNT_LEN);
+        data.resize(METADATA_ACCOUNT_LEN, 0);
+        data
+    }
+
+    fn creator(verified: bool, share: u8) -> Creator {
+        Creator {
+            address: mpl(&Pubkey::new_unique()),
+            verified,
+            share,
+        }
+    }
+
+    #[test]
+    fn parses_v1_metadata_with_creators() {
+        let mint = Pubkey::new_unique();
+        let metadata = Metadata {
+            creators: Some(vec![
+                creator(true, 0),
+                creator(false, 60),
+                creator(false, 40),
+            ]),
+            edition_nonce: Some(254),
+            ..metadata(&mint)
+        };
+
+        let parsed = parse_metadata(&account_data(&metadata)).unwrap();
+
+        assert_eq!(parsed.mint, mint);
+        assert_eq!(parsed.name, "Degen #1234");
+        assert_eq!(parsed.token_standard, None);
+        assert_eq!(parsed.verified_collection, None);
+    }
+
+    #[test]
+    fn parses_metadata_ending_before_optional_fields() {
+        let mint = Pubkey::new_unique();
+        let metadata = Metadata {
+            creators: Some(vec![creator(true, 100)]),
+            ..metadata(&mint)
+        };
+        let data = metadata.try_to_vec().unwrap();
+        // Drop edition nonce, token standard, collection, uses, collection
+        // details and programmable config, all `None`.
+        let data = &data[..data.len() - 6];
+
+        let parsed = parse_metadata(data).unwrap();
+
+        assert_eq!(parsed.mint, mint);
+        assert_eq!(parsed.token_standard, None);
+        assert_eq!(parsed.verified_collection, None);
+    }
+
+    #[test]
+    fn parses_verified_collection() {
+        let mint = Pubkey::new_unique();
+        let collection = Pubkey::new_unique();
+        let metadata = Metadata {
+            creators: Some(vec![creator(true, 100)]),
+            edition_nonce: Some(255),
+            token_standard: Some(MetadataTokenStandard::NonFungible),
+            collection: Some(Collection {
+                verified: true,
+                key: mpl(&collection),
+            }),
+            ..metadata(&mint)
+        };
+
+        let parsed = parse_metadata(&account_data(&metadata)).unwrap();
+
+        assert_eq!(parsed.mint, mint);
+        assert_eq!(parsed.token_standard, Some(TokenStandard::NonFungible));
+        assert_eq!(parsed.verified_collection, Some(collection));
+    }
+
+    #[test]
+    fn ignores_unverified_collection() {
+        let metadata = Metadata {
+            token_standard: Some(MetadataTokenStandard::NonFungible),
+            collection: Some(Collection {
+                verified: false,
+                key: mpl(&Pubkey::new_unique()),
+            }),
+            ..metadata(&Pubkey::new_unique())
+        };
+
+        let parsed = parse_metadata(&account_data(&metadata)).unwrap();
+
+        assert_eq!(parsed.verified_collection, None);
+    }
+
+    #[test]
+    fn parses_programmable_nft() {
+        let mint = Pubkey::new_unique();
+        let collection = Pubkey::new_unique();
+        let metadata = Metadata {
+            creators: Some(vec![creator(true, 0), creator(false, 100)]),
+            edition_nonce: Some(253),
+            token_standard: Some(MetadataTokenStandard::ProgrammableNonFungible),
+            collection: Some(Collection {
+                verified: true,
+                key: mpl(&collection),
+            }),
+            programmable_config: Some(ProgrammableConfig::V1 {
+                rule_set: Some(mpl(&Pubkey::new_unique())),
+            }),
+            ..metadata(&mint)
+        };
+
+        let parsed = parse_metadata(&account_data(&metadata)).unwrap();
+
+        assert_eq!(parsed.mint, mint);
+        assert_eq!(
+            parsed.token_standard,
+            Some(TokenStandard::ProgrammableNonFungible)
+        );
+        assert!(parsed.token_standard.unwrap().is_programmable());
+        assert_eq!(parsed.verified_collection, Some(collection));
+    }
+
+    #[test]
+    fn rejects_other_accounts() {
+        let metadata = Metadata {
+            creators: Some(vec![creator(true, 100)]),
+            ..metadata(&Pubkey::new_unique())
+        };
+        let mut data = account_data(&metadata);
+
+        // Key, update authority, mint, name, symbol, uri, seller fee basis
+        // points, then the first creator
+        let creators = 1 + 32 + 32 + (4 + MAX_NAME_LENGTH) + (4 + MAX_SYMBOL_LENGTH);
+        let creators = creators + (4 + MAX_URI_LENGTH) + 2 + 1 + 4;
+        assert!(parse_metadata(&data[..creators + 20]).is_none());
+        data[0] = KEY_MASTER_EDITION_V2;
+        assert!(parse_metadata(&data).is_none());
+        assert!(parse_metadata(&[]).is_none());
+    }
+
+    #[test]
+    fn derives_token_metadata_addresses() {
+        let mint = Pubkey::new_unique();
+        let token = Pubkey::new_unique();
+
+        assert_eq!(
+            metadata_address(&mint).to_bytes(),
+            Metadata::find_pda(&mpl(&mint)).0.to_bytes()
+        );
+        assert_eq!(
+            edition_address(&mint).to_bytes(),
+            MasterEdition::find_pda(&mpl(&mint)).0.to_bytes()
+        );
+        assert_eq!(
+            token_record_address(&mint, &token).to_bytes(),
+            TokenRecord::find_pda(&mpl(&mint), &mpl(&token))
+                .0
+                .to_bytes()
+        );
+    }
+
+    /// Asserts that `instruction` matches the one built by
+    /// `mpl-token-metadata`, account by account.
+    fn assert_matches_builder(instruction: &Instruction, builder: &BurnV1Builder) {
+        let expected = builder.instruction();
+        assert_eq!(
+            instruction.program_id.to_bytes(),
+            expected.program_id.to_bytes()
+        );
+        let instruction_accounts: Vec<([u8; 32], bool, bool)> = instruction
+            .accounts
+            .iter()
+            .map(|meta| (meta.pubkey.to_bytes(), meta.is_signer, meta.is_writable))
+            .collect();
+        let expected_accounts: Vec<([u8; 32], bool, bool)> = expected
+            .accounts
+            .iter()
+            .map(|meta| (meta.pubkey.to_bytes(), meta.is_signer, meta.is_writable))
+            .collect();
+        assert_eq!(instruction_accounts, expected_accounts);
+        assert_eq!(instruction.data, expected.data);
+    }
+
+    #[test]
+    fn burn_v1_matches_token_metadata_layout() {
+        let authority = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token = Pubkey::new_unique();
+        let nft = MetaplexNft {
+            name: String::new(),
+            metadata: metadata_address(&mint),
+            edition: edition_address(&mint),
+            token_record: None,
+            collection_metadata: None,
+            token_standard: TokenStandard::NonFungible,
+            lamports: 0,
+        };
+
+        let instruction = burn_v1(&authority, &nft, &mint, &token, &spl_token::id(), 1);
+
+        assert_eq!(instruction.data, [41, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(instruction.accounts.len(), 14);
+        assert_eq!(instruction.accounts[0], AccountMeta::new(authority, true));
+        assert_eq!(
+            instruction.accounts[2],
+            AccountMeta::new(nft.metadata, false)
+        );
+        assert_eq!(
+            instruction.accounts[3],
+            AccountMeta::new(nft.edition, false)
+        );
+        assert_eq!(instruction.accounts[4], AccountMeta::new(mint, false));
+        assert_eq!(instruction.accounts[5], AccountMeta::new(token, false));
+        assert_matches_builder(
+            &instruction,
+            BurnV1Builder::new()
+                .authority(mpl(&authority))
+                .metadata(mpl(&nft.metadata))
+                .edition(Some(mpl(&nft.edition)))
+                .mint(mpl(&mint))
+                .token(mpl(&token))
+                .spl_token_program(mpl(&spl_token::id()))
+                .amount(1),
+        );
+    }
+
+    #[test]
+    fn burn_v1_matches_token_metadata_layout_for_programmable_nft() {
+        let authority = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token = Pubkey::new_unique();
+        let collection = Pubkey::new_unique();
+        let nft = MetaplexNft {
+            name: String::new(),
+            metadata: metadata_address(&mint),
+            edition: edition_address(&mint),
+            token_record: Some(token_record_address(&mint, &token)),
+            collection_metadata: Some(metadata_address(&collection)),
+            token_standard: TokenStandard::ProgrammableNonFungible,
+            lamports: 0,
+        };
+
+        let instruction = burn_v1(&authority, &nft, &mint, &token, &spl_token_2022::id(), 1);
+
+        assert_eq!(
+            instruction.accounts[1],
+            AccountMeta::new(metadata_address(&collection), false)
+        );
+        assert_eq!(
+            instruction.accounts[10],
+            AccountMeta::new(nft.token_record.unwrap(), false)
+        );
+        assert_matches_builder(
+            &instruction,
+            BurnV1Builder::new()
+                .authority(mpl(&authority))
+                .collection_metadata(Some(mpl(&metadata_address(&collection))))
+                .metadata(mpl(&nft.metadata))
+                .edition(Some(mpl(&nft.edition)))
+                .mint(mpl(&mint))
+                .token(mpl(&token))
+                .token_record(Some(mpl(&nft.token_record.unwrap())))
+                .spl_token_program(mpl(&spl_token_2022::id()))
+                .amount(1),
+        );
+    }
+}
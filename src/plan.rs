@@ -0,0 +1,381 @@
+use crate::{
+    discovery::UnreadableAccount, metaplex::TokenStandard, planner::NftPolicy,
+    protection::ProtectionRule, risk::MintRisk,
+};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use spl_token_2022::amount_to_ui_amount_string_trimmed;
+use std::fmt;
+
+/// What the planner decided to do with a single token account.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccountAction {
+    /// Burn the remaining balance, then close the account.
+    BurnAndClose { amount: u64 },
+    /// Burn an NFT through Token Metadata `BurnV1`, which also closes the
+    /// token account and the NFT's metadata, edition and token record.
+    BurnNft {
+        token_standard: TokenStandard,
+        metadata_lamports: u64,
+    },
+    /// Close an account that already holds no tokens.
+    Close,
+    /// Close a wrapped SOL account, returning the wrapped lamports along with
+    /// the rent. Burning would destroy real SOL.
+    Unwrap { amount: u64 },
+    /// Leave the account untouched.
+    Skip(SkipReason),
+}
+
+impl fmt::Display for AccountAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountAction::BurnAndClose { .. } => write!(f, "burn+close"),
+            AccountAction::BurnNft { .. } => write!(f, "burn nft"),
+            AccountAction::Close => write!(f, "close"),
+            AccountAction::Unwrap { .. } => write!(f, "unwrap"),
+            AccountAction::Skip(_) => write!(f, "skip"),
+        }
+    }
+}
+
+impl AccountAction {
+    /// Human-readable explanation of why this action was chosen.
+    pub fn reason(&self) -> String {
+        match self {
+            AccountAction::BurnAndClose { amount } => format!("holds {} tokens", amount),
+            AccountAction::BurnNft {
+                token_standard,
+                metadata_lamports,
+            } => format!(
+                "{} with Token Metadata accounts holding {} lamports",
+                token_standard, metadata_lamports
+            ),
+            AccountAction::Close => "empty account".to_string(),
+            AccountAction::Unwrap { amount } => {
+                format!("wrapped SOL account holding {} lamports", amount)
+            }
+            AccountAction::Skip(reason) => reason.to_string(),
+        }
+    }
+}
+
+/// Why an account was left untouched.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    /// The account or its mint is protected.
+    Protected(ProtectionRule),
+    /// The account is frozen and could not be thawed, either because thawing
+    /// is disabled or because the wallet is not the mint's freeze authority.
+    Frozen { can_thaw: bool },
+    /// The account still holds tokens and only empty accounts are closed.
+    NotEmpty { amount: u64 },
+    /// The account holds an NFT that the NFT policy does not allow burning.
+    Nft { policy: NftPolicy },
+    /// The balance is worth at least the burn threshold, in UI units.
+    AboveBurnThreshold { ui_amount: f64, threshold: f64 },
+    /// A burn threshold is set but the mint's decimals could not be fetched.
+    UnknownDecimals,
+    /// The balance is worth at least the USD burn threshold.
+    AboveValueThreshold { usd_value: f64, threshold: f64 },
+    /// A USD burn threshold is set but the balance could not be valued.
+    UnknownValue,
+    /// The account holds withheld transfer fees that cannot be harvested to
+    /// its mint, which blocks closing it.
+    WithheldFees { amount: u64 },
+    /// The account holds encrypted confidential transfer balances that the
+    /// wallet cannot empty, which blocks closing it.
+    ConfidentialBalance { reason: String },
+    /// The account holds tokens of a paused mint, which rejects burns.
+    Paused,
+    /// The account's close authority is another key, so the wallet cannot
+    /// close it.
+    CloseAuthority { authority: Pubkey },
+    /// The account was touched more recently than the inactivity threshold.
+    RecentlyActive { days_ago: u64, threshold: u64 },
+    /// An inactivity threshold is set but the account's last activity could
+    /// not be determined.
+    UnknownActivity,
+    /// The account does not match the account filter.
+    Filtered,
+    /// The account was left out when reviewing the plan.
+    Deselected,
+    /// The mint still has this much supply after the run's burns.
+    SupplyRemaining { supply: u64 },
+    /// Token accounts of the mint owned by the wallet are being kept.
+    AccountsKept { count: usize },
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Protected(rule) => write!(f, "{}", rule),
+            SkipReason::Frozen { can_thaw: true } => {
+                write!(f, "frozen account (thawing is disabled)")
+            }
+            SkipReason::Frozen { can_thaw: false } => {
+                write!(f, "frozen account (wallet is not the freeze authority)")
+            }
+            SkipReason::NotEmpty { amount } => {
+                write!(f, "holds {} tokens (close-empty-only mode)", amount)
+            }
+            SkipReason::Nft {
+                policy: NftPolicy::Ask,
+            } => write!(f, "NFT not approved for burning"),
+            SkipReason::Nft { policy } => write!(f, "NFT (policy: {})", policy),
+            SkipReason::AboveBurnThreshold {
+                ui_amount,
+                threshold,
+            } => write!(
+                f,
+                "balance {} is not below the burn threshold {}",
+                ui_amount, threshold
+            ),
+            SkipReason::UnknownDecimals => {
+                write!(f, "mint decimals unknown, cannot apply burn threshold")
+            }
+            SkipReason::AboveValueThreshold {
+                usd_value,
+                threshold,
+            } => write!(
+                f,
+                "worth ${:.2}, not below the ${:.2} burn threshold",
+                usd_value, threshold
+            ),
+            SkipReason::UnknownValue => {
+                write!(f, "no USD price, cannot apply value threshold")
+            }
+            SkipReason::WithheldFees { amount } => write!(
+                f,
+                "holds {} withheld transfer fee tokens that cannot be harvested (mint unavailable \
+                 or has no transfer fee config)",
+                amount
+            ),
+            SkipReason::ConfidentialBalance { reason } => {
+                write!(f, "confidential balance cannot be emptied ({})", reason)
+            }
+            SkipReason::Paused => write!(f, "mint is paused, burning is blocked"),
+            SkipReason::CloseAuthority { authority } => {
+                write!(f, "close authority is {}", authority)
+            }
+            SkipReason::RecentlyActive {
+                days_ago,
+                threshold,
+            } => write!(
+                f,
+                "active {} days ago, within the {}-day inactivity threshold",
+                days_ago, threshold
+            ),
+            SkipReason::UnknownActivity => {
+                write!(
+                    f,
+                    "last activity unknown, cannot apply inactivity threshold"
+                )
+            }
+            SkipReason::Filtered => write!(f, "does not match the filter"),
+            SkipReason::Deselected => write!(f, "deselected"),
+            SkipReason::SupplyRemaining { supply } => {
+                write!(f, "supply of {} remains after burning", supply)
+            }
+            SkipReason::AccountsKept { count } => {
+                write!(f, "{} of the wallet's token accounts are kept", count)
+            }
+        }
+    }
+}
+
+/// The planned handling of one token account.
+#[derive(Debug, Clone)]
+pub struct AccountPlan {
+    /// Address of the token account.
+    pub address: Pubkey,
+    /// Token program that owns the account.
+    pub program_id: Pubkey,
+    /// Mint of the tokens held by the account.
+    pub mint: Pubkey,
+    /// Raw token amount held by the account.
+    pub amount: u64,
+    /// Decimals of the mint, if it could be fetched.
+    pub decimals: Option<u8>,
+    /// USD value of the balance, if the mint was priced.
+    pub usd_value: Option<f64>,
+    /// Whether the account holds an NFT.
+    pub is_nft: bool,
+    /// Name of the NFT, if its Token Metadata was fetched.
+    pub name: Option<String>,
+    /// Lamports recovered when the account is closed, excluding any
+    /// Token Metadata accounts closed with it.
+    pub lamports: u64,
+    /// Delegate approved on the account and its delegated amount, if any.
+    pub delegate: Option<(Pubkey, u64)>,
+    /// Close authority set on the account, if any. Defaults to the owner.
+    pub close_authority: Option<Pubkey>,
+    /// Whether the account is frozen on chain, whatever the planned action.
+    pub frozen: bool,
+    /// Risky extensions of the mint, if it could be fetched.
+    pub mint_risks: Vec<MintRisk>,
+    /// What will be done with the account.
+    pub action: AccountAction,
+    /// Transfer fees withheld in the account.
+    pub withheld_amount: u64,
+    /// Whether the account is frozen and will be thawed first using the
+    /// wallet's freeze authority.
+    pub thaw: bool,
+    /// Whether withheld transfer fees will be harvested to the mint before
+    /// the account is closed.
+    pub harvest: bool,
+    /// Whether the account's confidential transfer balances will be applied
+    /// and emptied, with a locally generated zero-balance proof, before the
+    /// account is closed.
+    pub empty_confidential: bool,
+    /// Whether the delegate of a kept account will be revoked.
+    pub revoke_delegate: bool,
+    /// Instructions implementing `action`. Skipped accounts only carry a
+    /// revoke, if any.
+    pub instructions: Vec<Instruction>,
+}
+
+impl AccountPlan {
+    /// The balance in whole tokens, if the mint's decimals are known.
+    pub fn ui_amount(&self) -> Option<String> {
+        self.decimals
+            .map(|decimals| amount_to_ui_amount_string_trimmed(self.amount, decimals))
+    }
+
+    /// Returns `true` if the account will be closed.
+    pub fn closes(&self) -> bool {
+        !matches!(self.action, AccountAction::Skip(_))
+    }
+
+    /// Lamports returned to the wallet by this account's action, including
+    /// unwrapped SOL and the rent of a burned NFT's metadata.
+    pub fn recovered_lamports(&self) -> u64 {
+        match self.action {
+            AccountAction::Skip(_) => 0,
+            AccountAction::BurnNft {
+                metadata_lamports, ..
+            } => self.lamports + metadata_lamports,
+            _ => self.lamports,
+        }
+    }
+}
+
+/// What the planner decided to do with a mint closable by the wallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MintAction {
+    /// Close the mint once its token accounts are cleaned up.
+    Close,
+    /// Leave the mint untouched.
+    Skip(SkipReason),
+}
+
+impl fmt::Display for MintAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MintAction::Close => write!(f, "close"),
+            MintAction::Skip(reason) => write!(f, "skip ({})", reason),
+        }
+    }
+}
+
+/// The planned handling of a Token-2022 mint whose close authority is the
+/// wallet.
+#[derive(Debug, Clone)]
+pub struct MintPlan {
+    /// Address of the mint.
+    pub address: Pubkey,
+    /// Token program that owns the mint.
+    pub program_id: Pubkey,
+    /// Lamports recovered when the mint is closed.
+    pub lamports: u64,
+    /// Supply of the mint before this run's burns.
+    pub supply: u64,
+    /// What will be done with the mint.
+    pub action: MintAction,
+    /// Instructions implementing `action`.
+    pub instructions: Vec<Instruction>,
+}
+
+impl MintPlan {
+    /// Returns `true` if the mint will be closed.
+    pub fn closes(&self) -> bool {
+        self.action == MintAction::Close
+    }
+}
+
+/// A group of accounts cleaned up together in one transaction.
+#[derive(Debug, Clone, Default)]
+pub struct Batch {
+    /// Token accounts, or mints, handled by the batch, in instruction order.
+    pub accounts: Vec<Pubkey>,
+    /// Every instruction of those accounts, excluding the compute budget
+    /// instructions added by the executor.
+    pub instructions: Vec<Instruction>,
+}
+
+/// Everything a cleanup run will do, computed before anything is signed.
+#[derive(Debug, Clone)]
+pub struct CleanupPlan {
+    /// Wallet that owns the token accounts and pays for the transactions.
+    pub owner: Pubkey,
+    /// Per-account decisions, in discovery order.
+    pub accounts: Vec<AccountPlan>,
+    /// Accounts skipped because their data could not be read.
+    pub unreadable: Vec<UnreadableAccount>,
+    /// Decisions for the mints the wallet can close. Mints are closed in
+    /// batches of their own, after every token account batch.
+    pub mints: Vec<MintPlan>,
+    /// SPL Memo attached to every transaction, if any.
+    pub memo: Option<String>,
+    /// Transactions to send, in order.
+    pub batches: Vec<Batch>,
+}
+
+impl CleanupPlan {
+    /// Returns `true` if the plan contains nothing to send.
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    /// Total number of cleanup instructions across all batches.
+    pub fn instruction_count(&self) -> usize {
+        self.batches
+            .iter()
+            .map(|batch| batch.instructions.len())
+            .sum()
+    }
+
+    /// Wrapped SOL lamports returned by closing native accounts, excluding
+    /// their rent.
+    pub fn unwrapped_lamports(&self) -> u64 {
+        self.accounts
+            .iter()
+            .filter_map(|account| match account.action {
+                AccountAction::Unwrap { amount } => Some(amount),
+                _ => None,
+            })
+            .sum()
+    }
+
+    /// Rent-exempt lamports recovered by closing every planned account and
+    /// mint.
+    pub fn rent_lamports(&self) -> u64 {
+        self.expected_lamports() - self.unwrapped_lamports()
+    }
+
+    /// Lamports returned to the wallet once every planned account and mint is
+    /// closed, including unwrapped SOL and the rent of burned NFTs' metadata.
+    pub fn expected_lamports(&self) -> u64 {
+        let accounts: u64 = self
+            .accounts
+            .iter()
+            .map(AccountPlan::recovered_lamports)
+            .sum();
+        let mints: u64 = self
+            .mints
+            .iter()
+            .filter(|mint| mint.closes())
+            .map(|mint| mint.lamports)
+            .sum();
+        accounts + mints
+    }
+}
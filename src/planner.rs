@@ -0,0 +1,1276 @@
+use crate::{
+    batcher::{pack_accounts, pack_mints, BatchLimits},
+    confidential::ConfidentialBalance,
+    discovery::{DiscoveredAccounts, MintInfo, TokenAccountInfo},
+    filter::Filter,
+    metaplex::burn_v1,
+    plan::{AccountAction, AccountPlan, CleanupPlan, MintAction, MintPlan, SkipReason},
+    protection::ProtectionRules,
+    registry::Cluster,
+    risk::MintRisk,
+};
+use anyhow::{bail, Result};
+use log::info;
+use solana_sdk::{clock::SECONDS_PER_DAY, program_option::COption, pubkey::Pubkey};
+use spl_token_2022::{
+    amount_to_ui_amount,
+    extension::{transfer_fee::instruction::harvest_withheld_tokens_to_mint, ExtensionType},
+    instruction::{burn, close_account, revoke, thaw_account},
+};
+use std::{
+    collections::HashSet,
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How token accounts holding NFTs are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NftPolicy {
+    /// Never burn NFTs.
+    #[default]
+    Keep,
+    /// Burn NFTs like any other balance.
+    Burn,
+    /// Burn only the NFT accounts listed in [`PlannerConfig::approved_nfts`].
+    Ask,
+}
+
+impl fmt::Display for NftPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NftPolicy::Keep => write!(f, "keep"),
+            NftPolicy::Burn => write!(f, "burn"),
+            NftPolicy::Ask => write!(f, "ask"),
+        }
+    }
+}
+
+impl FromStr for NftPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "keep" => Ok(NftPolicy::Keep),
+            "burn" => Ok(NftPolicy::Burn),
+            "ask" => Ok(NftPolicy::Ask),
+            _ => bail!("Unknown NFT policy: {} (expected keep, burn or ask)", s),
+        }
+    }
+}
+
+/// Options controlling which accounts are cleaned up and how they are batched.
+#[derive(Debug, Clone)]
+pub struct PlannerConfig {
+    /// Mints and token accounts that must never be burned or closed
+    pub protection: ProtectionRules,
+    /// Only accounts matching this filter are touched
+    pub filter: Option<Filter>,
+    /// Only touch accounts whose last transaction is at least this many days
+    /// old, using the activity recorded on each account. Accounts with
+    /// unknown activity are kept.
+    pub inactive_days: Option<u64>,
+    /// Only close accounts that already hold no tokens; never burn
+    pub close_empty_only: bool,
+    /// How accounts holding NFTs are handled
+    pub nft_policy: NftPolicy,
+    /// NFT token accounts approved for burning under [`NftPolicy::Ask`]
+    pub approved_nfts: HashSet<Pubkey>,
+    /// Only burn balances worth less than this many whole tokens (UI amount),
+    /// resolved per mint using its decimals. Larger balances are kept.
+    pub burn_below: Option<f64>,
+    /// Only burn balances worth less than this many US dollars, using the
+    /// prices recorded on each account. Unpriced balances are kept.
+    pub burn_below_usd: Option<f64>,
+    /// Burn balances of mints with suspicious extensions (permanent delegate,
+    /// non-transferable, transfer hook) even when they are above the burn
+    /// thresholds or cannot be priced
+    pub burn_risky: bool,
+    /// Thaw frozen accounts whose mint's freeze authority is the wallet
+    /// instead of skipping them
+    pub thaw_frozen: bool,
+    /// Revoke outstanding delegations on accounts that are kept. Closed
+    /// accounts need no revoke since closing removes the delegation.
+    pub revoke_delegates: bool,
+    /// SPL Memo attached to every transaction, such as a run identifier. Its
+    /// size counts against the batch limits.
+    pub memo: Option<String>,
+    /// Size, account and instruction limits for each transaction
+    pub batch_limits: BatchLimits,
+}
+
+impl Default for PlannerConfig {
+    fn default() -> Self {
+        Self {
+            protection: ProtectionRules::builtin(Cluster::MainnetBeta),
+            filter: None,
+            inactive_days: None,
+            close_empty_only: false,
+            nft_policy: NftPolicy::Keep,
+            approved_nfts: HashSet::new(),
+            burn_below: None,
+            burn_below_usd: None,
+            burn_risky: false,
+            thaw_frozen: false,
+            revoke_delegates: false,
+            memo: None,
+            batch_limits: BatchLimits::default(),
+        }
+    }
+}
+
+/// Decides what to do with each token account, and with each mint the wallet
+/// can close, and groups the resulting instructions into batches. Nothing is
+/// sent to the chain.
+pub fn plan_cleanup(
+    owner: &Pubkey,
+    discovered: DiscoveredAccounts,
+    config: &PlannerConfig,
+) -> Result<CleanupPlan> {
+    let DiscoveredAccounts {
+        token_accounts,
+        unreadable,
+        closable_mints,
+    } = discovered;
+    let mut accounts = Vec::with_capacity(token_accounts.len());
+
+    for token_account in token_accounts {
+        let mut account_plan = plan_account(owner, token_account, config)?;
+        log_account_plan(&account_plan, config);
+
+        // Kept accounts can still have a stale delegation revoked, unless they
+        // are frozen, which rejects revokes as well whatever the account was
+        // kept for, or filtered out.
+        if config.revoke_delegates
+            && !account_plan.closes()
+            && account_plan.delegate.is_some()
+            && !account_plan.frozen
+            && account_plan.action != AccountAction::Skip(SkipReason::Filtered)
+        {
+            info!(
+                "Revoking delegate of kept account: {}",
+                account_plan.address
+            );
+            account_plan.revoke_delegate = true;
+            account_plan.instructions.push(revoke(
+                &account_plan.program_id,
+                &account_plan.address,
+                owner,
+                &[],
+            )?);
+        }
+
+        accounts.push(account_plan);
+    }
+
+    let mints = closable_mints
+        .into_iter()
+        .map(|mint_info| plan_mint(owner, mint_info, &accounts, config))
+        .collect::<Result<Vec<_>>>()?;
+
+    let memo = config.memo.as_deref();
+    let mut batches = pack_accounts(owner, &accounts, memo, &config.batch_limits)?;
+    batches.extend(pack_mints(owner, &mints, memo, &config.batch_limits)?);
+
+    Ok(CleanupPlan {
+        owner: *owner,
+        accounts,
+        unreadable,
+        mints,
+        memo: config.memo.clone(),
+        batches,
+    })
+}
+
+/// Keeps only the `selected` accounts of `plan` and repacks its batches.
+///
+/// Every other account that would have been closed is skipped as
+/// [`SkipReason::Deselected`]; accounts that were already skipped are left
+/// as they are. Mints that can no longer be emptied are skipped as well.
+pub fn apply_selection(
+    mut plan: CleanupPlan,
+    selected: &HashSet<Pubkey>,
+    limits: &BatchLimits,
+) -> Result<CleanupPlan> {
+    for account_plan in &mut plan.accounts {
+        if account_plan.closes() && !selected.contains(&account_plan.address) {
+            info!("Skipping deselected account: {}", account_plan.address);
+            account_plan.action = AccountAction::Skip(SkipReason::Deselected);
+            account_plan.thaw = false;
+            account_plan.harvest = false;
+            account_plan.empty_confidential = false;
+            account_plan.instructions.clear();
+        }
+    }
+
+    for mint_plan in &mut plan.mints {
+        if let Some(reason) = mint_blocker(mint_plan.address, mint_plan.supply, &plan.accounts)
+            .filter(|_| mint_plan.closes())
+        {
+            info!("Keeping mint: {} ({})", mint_plan.address, reason);
+            mint_plan.action = MintAction::Skip(reason);
+            mint_plan.instructions.clear();
+        }
+    }
+
+    let memo = plan.memo.as_deref();
+    let mut batches = pack_accounts(&plan.owner, &plan.accounts, memo, limits)?;
+    batches.extend(pack_mints(&plan.owner, &plan.mints, memo, limits)?);
+    plan.batches = batches;
+    Ok(plan)
+}
+
+/// Lists the NFT token accounts in `discovered` that [`plan_cleanup`] would
+/// burn if the user approved them, so [`NftPolicy::Ask`] only asks about
+/// NFTs that are not kept anyway, whether protected, filtered out, frozen or
+/// otherwise.
+pub fn nft_burn_candidates<'a>(
+    owner: &Pubkey,
+    discovered: &'a DiscoveredAccounts,
+    config: &PlannerConfig,
+) -> Result<Vec<&'a TokenAccountInfo>> {
+    let config = PlannerConfig {
+        nft_policy: NftPolicy::Burn,
+        ..config.clone()
+    };
+
+    let mut candidates = Vec::new();
+    for token_account in &discovered.token_accounts {
+        if !token_account.is_nft() || token_account.account.amount == 0 {
+            continue;
+        }
+        if plan_account(owner, token_account.clone(), &config)?.closes() {
+            candidates.push(token_account);
+        }
+    }
+    Ok(candidates)
+}
+
+/// Decides whether a mint whose close authority is the wallet is closed
+/// after `accounts` are cleaned up.
+fn plan_mint(
+    owner: &Pubkey,
+    mint_info: MintInfo,
+    accounts: &[AccountPlan],
+    config: &PlannerConfig,
+) -> Result<MintPlan> {
+    let mut mint_plan = MintPlan {
+        address: mint_info.address,
+        program_id: mint_info.program_id,
+        lamports: mint_info.lamports,
+        supply: mint_info.mint.supply,
+        action: MintAction::Close,
+        instructions: Vec::new(),
+    };
+
+    if let Some(rule) = config
+        .protection
+        .check(&mint_info.address, &mint_info.address)
+    {
+        info!("Keeping protected mint: {} ({})", mint_info.address, rule);
+        mint_plan.action = MintAction::Skip(SkipReason::Protected(rule.clone()));
+        return Ok(mint_plan);
+    }
+
+    if let Some(reason) = mint_blocker(mint_info.address, mint_info.mint.supply, accounts) {
+        info!("Keeping mint: {} ({})", mint_info.address, reason);
+        mint_plan.action = MintAction::Skip(reason);
+        return Ok(mint_plan);
+    }
+
+    info!("Closing mint: {}", mint_info.address);
+    mint_plan.instructions.push(close_account(
+        &mint_info.program_id,
+        &mint_info.address,
+        owner,
+        owner,
+        &[],
+    )?);
+    Ok(mint_plan)
+}
+
+/// Why a mint cannot be closed once `accounts` are cleaned up, if it cannot.
+///
+/// Only a mint with no supply can be closed, so every token the mint has
+/// outstanding must sit in the wallet's accounts and be burned in this run.
+/// Mints of accounts the wallet keeps are kept too, even when those accounts
+/// are empty.
+fn mint_blocker(mint: Pubkey, supply: u64, accounts: &[AccountPlan]) -> Option<SkipReason> {
+    let mint_accounts = accounts.iter().filter(|account| account.mint == mint);
+
+    let kept = mint_accounts
+        .clone()
+        .filter(|account| !account.closes())
+        .count();
+    if kept > 0 {
+        return Some(SkipReason::AccountsKept { count: kept });
+    }
+
+    let burned: u64 = mint_accounts.map(|account| account.amount).sum();
+    let remaining = supply.saturating_sub(burned);
+    (remaining > 0).then_some(SkipReason::SupplyRemaining { supply: remaining })
+}
+
+/// Decides what to do with one token account and builds its instructions.
+///
+/// Nothing is logged here, since [`nft_burn_candidates`] previews decisions
+/// before the plan is made; [`plan_cleanup`] logs them with
+/// [`log_account_plan`].
+fn plan_account(
+    owner: &Pubkey,
+    token_account: TokenAccountInfo,
+    config: &PlannerConfig,
+) -> Result<AccountPlan> {
+    let is_nft = token_account.is_nft();
+    let filtered_out = config
+        .filter
+        .as_ref()
+        .is_some_and(|filter| !filter.matches(&token_account));
+    let TokenAccountInfo {
+        address,
+        program_id,
+        lamports,
+        account: token_account_data,
+        withheld_amount,
+        confidential,
+        mint_info,
+        usd_price,
+        metaplex,
+        last_activity,
+        ..
+    } = token_account;
+
+    let mut account_plan = AccountPlan {
+        address,
+        program_id,
+        mint: token_account_data.mint,
+        amount: token_account_data.amount,
+        decimals: mint_info.as_ref().map(|mint_info| mint_info.mint.decimals),
+        usd_value: None,
+        is_nft,
+        name: metaplex.as_ref().map(|nft| nft.name.clone()),
+        lamports,
+        delegate: Option::from(token_account_data.delegate)
+            .map(|delegate| (delegate, token_account_data.delegated_amount)),
+        close_authority: token_account_data.close_authority.into(),
+        frozen: token_account_data.is_frozen(),
+        mint_risks: mint_info
+            .as_ref()
+            .map(|mint_info| mint_info.risks.clone())
+            .unwrap_or_default(),
+        action: AccountAction::Close,
+        withheld_amount,
+        thaw: false,
+        harvest: false,
+        empty_confidential: false,
+        revoke_delegate: false,
+        instructions: Vec::new(),
+    };
+
+    if let Some(rule) = config.protection.check(&address, &token_account_data.mint) {
+        account_plan.action = AccountAction::Skip(SkipReason::Protected(rule.clone()));
+        return Ok(account_plan);
+    }
+
+    if filtered_out {
+        account_plan.action = AccountAction::Skip(SkipReason::Filtered);
+        return Ok(account_plan);
+    }
+
+    if let Some(threshold) = config.inactive_days {
+        let Some(last_activity) = last_activity else {
+            account_plan.action = AccountAction::Skip(SkipReason::UnknownActivity);
+            return Ok(account_plan);
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+        let days_ago =
+            now.saturating_sub(u64::try_from(last_activity).unwrap_or(0)) / SECONDS_PER_DAY;
+        if days_ago < threshold {
+            account_plan.action = AccountAction::Skip(SkipReason::RecentlyActive {
+                days_ago,
+                threshold,
+            });
+            return Ok(account_plan);
+        }
+    }
+
+    // Only the close authority, which defaults to the owner, can close the
+    // account or hand that authority to someone else.
+    if let COption::Some(authority) = token_account_data.close_authority {
+        if authority != *owner {
+            account_plan.action = AccountAction::Skip(SkipReason::CloseAuthority { authority });
+            return Ok(account_plan);
+        }
+    }
+
+    // Checked before thawing so a kept account never carries a thaw
+    if config.close_empty_only && token_account_data.amount > 0 {
+        account_plan.action = AccountAction::Skip(SkipReason::NotEmpty {
+            amount: token_account_data.amount,
+        });
+        return Ok(account_plan);
+    }
+
+    if is_nft && token_account_data.amount > 0 {
+        let approved = match config.nft_policy {
+            NftPolicy::Keep => false,
+            NftPolicy::Burn => true,
+            NftPolicy::Ask => config.approved_nfts.contains(&address),
+        };
+        if !approved {
+            account_plan.action = AccountAction::Skip(SkipReason::Nft {
+                policy: config.nft_policy,
+            });
+            return Ok(account_plan);
+        }
+    }
+
+    // Paused mints reject burns until the pause authority resumes them;
+    // empty accounts can still be closed
+    if token_account_data.amount > 0
+        && !token_account_data.is_native()
+        && account_plan.mint_risks.iter().any(MintRisk::blocks_burn)
+    {
+        account_plan.action = AccountAction::Skip(SkipReason::Paused);
+        return Ok(account_plan);
+    }
+
+    if let (Some(price), Some(decimals)) = (usd_price, account_plan.decimals) {
+        account_plan.usd_value =
+            Some(amount_to_ui_amount(token_account_data.amount, decimals) * price);
+    }
+
+    // Airdrops of suspicious mints are not worth keeping whatever their
+    // balance or quoted price
+    let burn_regardless = config.burn_risky
+        && token_account_data.amount > 0
+        && account_plan.mint_risks.iter().any(MintRisk::is_suspicious);
+
+    if let Some(threshold) = config.burn_below.filter(|_| !burn_regardless) {
+        if token_account_data.amount > 0 && !token_account_data.is_native() {
+            let Some(decimals) = account_plan.decimals else {
+                account_plan.action = AccountAction::Skip(SkipReason::UnknownDecimals);
+                return Ok(account_plan);
+            };
+
+            let ui_amount = amount_to_ui_amount(token_account_data.amount, decimals);
+            if ui_amount >= threshold {
+                account_plan.action = AccountAction::Skip(SkipReason::AboveBurnThreshold {
+                    ui_amount,
+                    threshold,
+                });
+                return Ok(account_plan);
+            }
+        }
+    }
+
+    if let Some(threshold) = config.burn_below_usd.filter(|_| !burn_regardless) {
+        if token_account_data.amount > 0 && !token_account_data.is_native() {
+            let Some(usd_value) = account_plan.usd_value else {
+                account_plan.action = AccountAction::Skip(SkipReason::UnknownValue);
+                return Ok(account_plan);
+            };
+
+            if usd_value >= threshold {
+                account_plan.action = AccountAction::Skip(SkipReason::AboveValueThreshold {
+                    usd_value,
+                    threshold,
+                });
+                return Ok(account_plan);
+            }
+        }
+    }
+
+    // Withheld fees block closing until they are moved to the mint, which
+    // anyone can do while the mint exists and still has its fee config.
+    if withheld_amount > 0
+        && !mint_info.as_ref().is_some_and(|mint_info| {
+            mint_info
+                .extensions
+                .contains(&ExtensionType::TransferFeeConfig)
+        })
+    {
+        account_plan.action = AccountAction::Skip(SkipReason::WithheldFees {
+            amount: withheld_amount,
+        });
+        return Ok(account_plan);
+    }
+
+    // Confidential balances block closing until they are emptied, which needs
+    // the owner's ElGamal key and a zero-balance proof prepared ahead of time
+    let empty_confidential = match confidential {
+        Some(ConfidentialBalance::Emptiable(instructions)) => instructions,
+        Some(ConfidentialBalance::Unchecked(_)) => {
+            account_plan.action = AccountAction::Skip(SkipReason::ConfidentialBalance {
+                reason: "encrypted balances were not checked".to_string(),
+            });
+            return Ok(account_plan);
+        }
+        Some(ConfidentialBalance::Blocked(reason)) => {
+            account_plan.action = AccountAction::Skip(SkipReason::ConfidentialBalance { reason });
+            return Ok(account_plan);
+        }
+        Some(ConfidentialBalance::Empty) | None => Vec::new(),
+    };
+
+    // Programmable NFTs are always frozen by their edition; BurnV1 thaws them
+    let metaplex = metaplex.filter(|_| is_nft && token_account_data.amount > 0);
+    let frozen_by_edition = metaplex
+        .as_ref()
+        .is_some_and(|nft| nft.token_standard.is_programmable());
+
+    if token_account_data.is_frozen() && !frozen_by_edition {
+        let can_thaw = mint_info
+            .as_ref()
+            .is_some_and(|mint_info| mint_info.mint.freeze_authority == COption::Some(*owner));
+
+        if !(config.thaw_frozen && can_thaw) {
+            account_plan.action = AccountAction::Skip(SkipReason::Frozen { can_thaw });
+            return Ok(account_plan);
+        }
+
+        // Frozen accounts reject both burn and close until thawed
+        account_plan.thaw = true;
+        account_plan.instructions.push(thaw_account(
+            &program_id,
+            &address,
+            &token_account_data.mint,
+            owner,
+            &[],
+        )?);
+    }
+
+    if withheld_amount > 0 {
+        account_plan.harvest = true;
+        account_plan
+            .instructions
+            .push(harvest_withheld_tokens_to_mint(
+                &program_id,
+                &token_account_data.mint,
+                &[&address],
+            )?);
+    }
+
+    if !empty_confidential.is_empty() {
+        account_plan.empty_confidential = true;
+        account_plan.instructions.extend(empty_confidential);
+    }
+
+    if let Some(nft) = metaplex {
+        // BurnV1 closes the token account itself, so no close follows
+        account_plan.action = AccountAction::BurnNft {
+            token_standard: nft.token_standard,
+            metadata_lamports: nft.lamports,
+        };
+        account_plan.instructions.push(burn_v1(
+            owner,
+            &nft,
+            &token_account_data.mint,
+            &address,
+            &program_id,
+            token_account_data.amount,
+        ));
+        return Ok(account_plan);
+    }
+
+    if token_account_data.is_native() {
+        // Closing a wrapped SOL account unwraps it; the token program
+        // rejects burns from native accounts anyway.
+        account_plan.action = AccountAction::Unwrap {
+            amount: token_account_data.amount,
+        };
+    } else if token_account_data.amount > 0 {
+        // Burn the remaining balance so the account can be closed
+
+        account_plan.action = AccountAction::BurnAndClose {
+            amount: token_account_data.amount,
+        };
+        account_plan.instructions.push(burn(
+            &program_id,
+            &address,
+            &token_account_data.mint,
+            owner,
+            &[],
+            token_account_data.amount,
+        )?);
+    }
+
+    // Always close the account to recover SOL
+    account_plan
+        .instructions
+        .push(close_account(&program_id, &address, owner, owner, &[])?);
+
+    Ok(account_plan)
+}
+
+/// Logs what [`plan_account`] decided for an account.
+fn log_account_plan(account_plan: &AccountPlan, config: &PlannerConfig) {
+    let address = account_plan.address;
+    let mint = account_plan.mint;
+
+    if let AccountAction::Skip(reason) = &account_plan.action {
+        info!("Skipping account: {} (mint: {}): {}", address, mint, reason);
+        return;
+    }
+
+    if account_plan.thaw {
+        info!("Thawing frozen account: {}", address);
+    }
+    if account_plan.harvest {
+        info!(
+            "Harvesting {} withheld transfer fee tokens from account: {}",
+            account_plan.withheld_amount, address
+        );
+    }
+    if account_plan.empty_confidential {
+        info!("Emptying confidential balance of account: {}", address);
+    }
+    if let Some((delegate, delegated_amount)) = account_plan.delegate {
+        info!(
+            "Account {} has delegate {} for {} tokens; closing removes it",
+            address, delegate, delegated_amount
+        );
+    }
+
+    match &account_plan.action {
+        AccountAction::BurnNft { token_standard, .. } => {
+            // BurnV1 closes the token account itself
+            info!(
+                "Burning {} through Token Metadata: {} (mint: {})",
+                token_standard, address, mint
+            );
+            return;
+        }
+        AccountAction::Unwrap { amount } => info!(
+            "Unwrapping {} lamports from wrapped SOL account: {}",
+            amount, address
+        ),
+        AccountAction::BurnAndClose { amount }
+            if config.burn_risky && account_plan.mint_risks.iter().any(MintRisk::is_suspicious) =>
+        {
+            info!(
+                "Burning {} tokens of suspicious mint regardless of thresholds: {} (mint: {})",
+                amount, address, mint
+            )
+        }
+        AccountAction::BurnAndClose { amount } => info!(
+            "Burning {} tokens from account: {} (mint: {})",
+            amount, address, mint
+        ),
+        AccountAction::Close | AccountAction::Skip(_) => {}
+    }
+    info!("Closing token account: {}", address);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        metaplex::{
+            edition_address, metadata_address, MetaplexNft, TokenStandard,
+            TOKEN_METADATA_PROGRAM_ID,
+        },
+        protection::RuleSource,
+        test_support::{token_account, TOKEN_ACCOUNT_RENT},
+    };
+    use solana_sdk::instruction::Instruction;
+    use spl_token_2022::{instruction::TokenInstruction, state::AccountState};
+    use std::path::PathBuf;
+
+    /// Plans `token_accounts` and `closable_mints` of `owner`.
+    fn plan(
+        owner: &Pubkey,
+        token_accounts: Vec<TokenAccountInfo>,
+        closable_mints: Vec<MintInfo>,
+        config: &PlannerConfig,
+    ) -> CleanupPlan {
+        let discovered = DiscoveredAccounts {
+            token_accounts,
+            closable_mints,
+            ..DiscoveredAccounts::default()
+        };
+        plan_cleanup(owner, discovered, config).unwrap()
+    }
+
+    /// Instructions of `plan` that burn tokens, burn an NFT or unwrap SOL.
+    fn burns_or_unwraps<'a>(plan: &'a CleanupPlan, wrapped_sol: &Pubkey) -> Vec<&'a Instruction> {
+        plan.batches
+            .iter()
+            .flat_map(|batch| &batch.instructions)
+            .filter(|instruction| {
+                instruction.program_id == TOKEN_METADATA_PROGRAM_ID
+                    || instruction
+                        .accounts
+                        .iter()
+                        .any(|meta| meta.pubkey == *wrapped_sol)
+                    || matches!(
+                        TokenInstruction::unpack(&instruction.data),
+                        Ok(TokenInstruction::Burn { .. } | TokenInstruction::BurnChecked { .. })
+                    )
+            })
+            .collect()
+    }
+
+    /// A Token-2022 account of `owner` holding `amount` tokens of a mint with
+    /// `supply` whose close authority is `owner`, and that mint.
+    fn closable_mint_holder(
+        owner: &Pubkey,
+        amount: u64,
+        supply: u64,
+    ) -> (TokenAccountInfo, MintInfo) {
+        let holder = token_account()
+            .owner(owner)
+            .program_id(&spl_token_2022::id())
+            .amount(amount)
+            .supply(supply)
+            .build();
+        let mut mint_info = holder.mint_info.clone().unwrap();
+        mint_info.close_authority = Some(*owner);
+        (holder, mint_info)
+    }
+
+    #[test]
+    fn nft_burn_candidates_leave_out_kept_nfts() {
+        let owner = Pubkey::new_unique();
+        let nft = || token_account().owner(&owner).nft();
+        let burnable = nft().build();
+        let protected = nft().build();
+        let filtered = nft().build();
+        let frozen = nft().frozen().build();
+        let fungible = nft().supply(1_000).build();
+        let empty = nft().amount(0).build();
+
+        let mut protection = ProtectionRules::empty();
+        protection.protect_mint(
+            protected.account.mint,
+            RuleSource::File {
+                path: PathBuf::from("protect.txt"),
+            },
+        );
+        let config = PlannerConfig {
+            protection,
+            filter: Some(Filter::parse(&format!("address != {}", filtered.address)).unwrap()),
+            nft_policy: NftPolicy::Ask,
+            ..PlannerConfig::default()
+        };
+        let discovered = DiscoveredAccounts {
+            token_accounts: vec![
+                burnable.clone(),
+                protected,
+                filtered,
+                frozen,
+                fungible,
+                empty,
+            ],
+            ..DiscoveredAccounts::default()
+        };
+
+        let candidates = nft_burn_candidates(&owner, &discovered, &config).unwrap();
+
+        assert_eq!(
+            candidates
+                .iter()
+                .map(|token_account| token_account.address)
+                .collect::<Vec<_>>(),
+            vec![burnable.address]
+        );
+    }
+
+    #[test]
+    fn approved_nft_burn_candidates_are_burned() {
+        let owner = Pubkey::new_unique();
+        let discovered = DiscoveredAccounts {
+            token_accounts: vec![token_account().owner(&owner).nft().build()],
+            ..DiscoveredAccounts::default()
+        };
+        let mut config = PlannerConfig {
+            nft_policy: NftPolicy::Ask,
+            ..PlannerConfig::default()
+        };
+
+        let candidates = nft_burn_candidates(&owner, &discovered, &config).unwrap();
+        config.approved_nfts = candidates
+            .iter()
+            .map(|token_account| token_account.address)
+            .collect();
+        let plan = plan_cleanup(&owner, discovered, &config).unwrap();
+
+        assert!(plan.accounts[0].closes());
+    }
+
+    #[test]
+    fn closes_mint_without_supply() {
+        let owner = Pubkey::new_unique();
+        let (_, mint_info) = closable_mint_holder(&owner, 0, 0);
+
+        let plan = plan(
+            &owner,
+            Vec::new(),
+            vec![mint_info.clone()],
+            &PlannerConfig::default(),
+        );
+
+        assert_eq!(plan.mints[0].action, MintAction::Close);
+        assert_eq!(
+            plan.mints[0].instructions,
+            vec![close_account(
+                &spl_token_2022::id(),
+                &mint_info.address,
+                &owner,
+                &owner,
+                &[]
+            )
+            .unwrap()]
+        );
+        assert_eq!(plan.batches.len(), 1);
+        assert_eq!(plan.batches[0].accounts, vec![mint_info.address]);
+    }
+
+    #[test]
+    fn closes_mint_emptied_by_the_run() {
+        let owner = Pubkey::new_unique();
+        let (holder, mint_info) = closable_mint_holder(&owner, 400, 400);
+
+        let plan = plan(
+            &owner,
+            vec![holder.clone()],
+            vec![mint_info.clone()],
+            &PlannerConfig::default(),
+        );
+
+        assert_eq!(
+            plan.accounts[0].action,
+            AccountAction::BurnAndClose { amount: 400 }
+        );
+        assert_eq!(plan.mints[0].action, MintAction::Close);
+        // The mint is closed in a later transaction than the burn
+        assert_eq!(plan.batches.len(), 2);
+        assert_eq!(plan.batches[0].accounts, vec![holder.address]);
+        assert_eq!(plan.batches[1].accounts, vec![mint_info.address]);
+    }
+
+    #[test]
+    fn keeps_mint_with_remaining_supply() {
+        let owner = Pubkey::new_unique();
+        let (holder, mint_info) = closable_mint_holder(&owner, 400, 1_000);
+
+        let plan = plan(
+            &owner,
+            vec![holder],
+            vec![mint_info],
+            &PlannerConfig::default(),
+        );
+
+        assert!(plan.accounts[0].closes());
+        assert_eq!(
+            plan.mints[0].action,
+            MintAction::Skip(SkipReason::SupplyRemaining { supply: 600 })
+        );
+        assert!(plan.mints[0].instructions.is_empty());
+        assert_eq!(plan.batches.len(), 1);
+    }
+
+    #[test]
+    fn keeps_mint_of_kept_account() {
+        let owner = Pubkey::new_unique();
+        let (mut holder, mint_info) = closable_mint_holder(&owner, 0, 0);
+        holder.account.state = AccountState::Frozen;
+
+        let plan = plan(
+            &owner,
+            vec![holder],
+            vec![mint_info],
+            &PlannerConfig::default(),
+        );
+
+        assert_eq!(
+            plan.accounts[0].action,
+            AccountAction::Skip(SkipReason::Frozen { can_thaw: false })
+        );
+        assert_eq!(
+            plan.mints[0].action,
+            MintAction::Skip(SkipReason::AccountsKept { count: 1 })
+        );
+        assert!(plan.batches.is_empty());
+    }
+
+    #[test]
+    fn deselecting_holder_keeps_mint() {
+        let owner = Pubkey::new_unique();
+        let (holder, mint_info) = closable_mint_holder(&owner, 400, 400);
+        let plan = plan(
+            &owner,
+            vec![holder],
+            vec![mint_info],
+            &PlannerConfig::default(),
+        );
+        assert_eq!(plan.mints[0].action, MintAction::Close);
+
+        let plan = apply_selection(plan, &HashSet::new(), &BatchLimits::default()).unwrap();
+
+        assert_eq!(
+            plan.accounts[0].action,
+            AccountAction::Skip(SkipReason::Deselected)
+        );
+        assert_eq!(
+            plan.mints[0].action,
+            MintAction::Skip(SkipReason::AccountsKept { count: 1 })
+        );
+        assert!(plan.mints[0].instructions.is_empty());
+        assert!(plan.batches.is_empty());
+    }
+
+    #[test]
+    fn close_empty_only_never_burns_or_unwraps() {
+        let owner = Pubkey::new_unique();
+        let empty = token_account().owner(&owner).build();
+        let holding = token_account().owner(&owner).amount(5).build();
+        let wrapped_sol = token_account().owner(&owner).wrapped_sol(1_000_000).build();
+        let mut nft = token_account().owner(&owner).nft().build();
+        nft.metaplex = Some(MetaplexNft {
+            name: "Degen #1234".to_string(),
+            metadata: metadata_address(&nft.account.mint),
+            edition: edition_address(&nft.account.mint),
+            token_record: None,
+            collection_metadata: None,
+            token_standard: TokenStandard::NonFungible,
+            lamports: 5_616_720,
+        });
+        let config = PlannerConfig {
+            close_empty_only: true,
+            nft_policy: NftPolicy::Burn,
+            ..PlannerConfig::default()
+        };
+
+        let plan = plan(
+            &owner,
+            vec![empty, holding, wrapped_sol.clone(), nft],
+            Vec::new(),
+            &config,
+        );
+
+        assert_eq!(plan.accounts[0].action, AccountAction::Close);
+        for (account_plan, amount) in plan.accounts[1..].iter().zip([5, 1_000_000, 1]) {
+            assert_eq!(
+                account_plan.action,
+                AccountAction::Skip(SkipReason::NotEmpty { amount })
+            );
+            assert!(account_plan.instructions.is_empty());
+        }
+        assert!(burns_or_unwraps(&plan, &wrapped_sol.address).is_empty());
+        assert_eq!(plan.batches[0].accounts, vec![plan.accounts[0].address]);
+    }
+
+    #[test]
+    fn burns_and_unwraps_without_close_empty_only() {
+        let owner = Pubkey::new_unique();
+        let holding = token_account().owner(&owner).amount(5).build();
+        let wrapped_sol = token_account().owner(&owner).wrapped_sol(1_000_000).build();
+
+        let plan = plan(
+            &owner,
+            vec![holding, wrapped_sol.clone()],
+            Vec::new(),
+            &PlannerConfig::default(),
+        );
+
+        assert_eq!(burns_or_unwraps(&plan, &wrapped_sol.address).len(), 2);
+    }
+
+    #[test]
+    fn skips_balance_of_paused_mint() {
+        let owner = Pubkey::new_unique();
+        let paused = vec![MintRisk::Pausable { paused: true }];
+        let holding = token_account()
+            .owner(&owner)
+            .amount(5)
+            .mint_risks(paused.clone())
+            .build();
+        let empty = token_account().owner(&owner).mint_risks(paused).build();
+        let resumed = token_account()
+            .owner(&owner)
+            .amount(5)
+            .mint_risks(vec![MintRisk::Pausable { paused: false }])
+            .build();
+        let config = PlannerConfig {
+            burn_risky: true,
+            ..PlannerConfig::default()
+        };
+
+        let plan = plan(&owner, vec![holding, empty, resumed], Vec::new(), &config);
+
+        assert_eq!(
+            plan.accounts[0].action,
+            AccountAction::Skip(SkipReason::Paused)
+        );
+        assert!(plan.accounts[0].instructions.is_empty());
+        assert_eq!(plan.accounts[1].action, AccountAction::Close);
+        assert_eq!(
+            plan.accounts[2].action,
+            AccountAction::BurnAndClose { amount: 5 }
+        );
+    }
+
+    #[test]
+    fn burn_risky_overrides_burn_thresholds() {
+        let owner = Pubkey::new_unique();
+        let suspicious = token_account()
+            .owner(&owner)
+            .amount(5_000_000)
+            .mint_risks(vec![MintRisk::PermanentDelegate {
+                delegate: Pubkey::new_unique(),
+            }])
+            .build();
+        let pausable = token_account()
+            .owner(&owner)
+            .amount(5_000_000)
+            .mint_risks(vec![MintRisk::Pausable { paused: false }])
+            .build();
+        let config = PlannerConfig {
+            burn_below: Some(1.0),
+            burn_below_usd: Some(1.0),
+            ..PlannerConfig::default()
+        };
+        let token_accounts = vec![suspicious, pausable];
+
+        let kept = plan(&owner, token_accounts.clone(), Vec::new(), &config);
+        let burned = plan(
+            &owner,
+            token_accounts,
+            Vec::new(),
+            &PlannerConfig {
+                burn_risky: true,
+                ..config
+            },
+        );
+
+        let above_threshold = AccountAction::Skip(SkipReason::AboveBurnThreshold {
+            ui_amount: 5.0,
+            threshold: 1.0,
+        });
+        assert_eq!(kept.accounts[0].action, above_threshold);
+        assert_eq!(
+            burned.accounts[0].action,
+            AccountAction::BurnAndClose { amount: 5_000_000 }
+        );
+        // Pausable mints are not suspicious, so the threshold still applies
+        assert_eq!(kept.accounts[1].action, above_threshold);
+        assert_eq!(burned.accounts[1].action, above_threshold);
+    }
+
+    #[test]
+    fn burns_only_balances_below_threshold() {
+        let owner = Pubkey::new_unique();
+        let token_accounts = [1_249, 1_250, 1_251]
+            .into_iter()
+            .map(|amount| {
+                token_account()
+                    .owner(&owner)
+                    .decimals(3)
+                    .amount(amount)
+                    .build()
+            })
+            .collect();
+        let config = PlannerConfig {
+            burn_below: Some(1.25),
+            ..PlannerConfig::default()
+        };
+
+        let plan = plan(&owner, token_accounts, Vec::new(), &config);
+
+        assert_eq!(
+            plan.accounts[0].action,
+            AccountAction::BurnAndClose { amount: 1_249 }
+        );
+        for (account_plan, ui_amount) in plan.accounts[1..].iter().zip([1.25, 1.251]) {
+            assert_eq!(
+                account_plan.action,
+                AccountAction::Skip(SkipReason::AboveBurnThreshold {
+                    ui_amount,
+                    threshold: 1.25,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn skips_frozen_accounts_by_default() {
+        let owner = Pubkey::new_unique();
+        let own_mint = token_account()
+            .owner(&owner)
+            .amount(5)
+            .freeze_authority(&owner)
+            .frozen()
+            .build();
+        let foreign_mint = token_account()
+            .owner(&owner)
+            .freeze_authority(&Pubkey::new_unique())
+            .frozen()
+            .build();
+
+        let kept = plan(
+            &owner,
+            vec![own_mint.clone(), foreign_mint.clone()],
+            Vec::new(),
+            &PlannerConfig::default(),
+        );
+        let thawed = plan(
+            &owner,
+            vec![own_mint, foreign_mint],
+            Vec::new(),
+            &PlannerConfig {
+                thaw_frozen: true,
+                ..PlannerConfig::default()
+            },
+        );
+
+        assert_eq!(
+            kept.accounts[0].action,
+            AccountAction::Skip(SkipReason::Frozen { can_thaw: true })
+        );
+        assert_eq!(
+            thawed.accounts[1].action,
+            AccountAction::Skip(SkipReason::Frozen { can_thaw: false })
+        );
+        for account_plan in [&kept.accounts[0], &kept.accounts[1], &thawed.accounts[1]] {
+            assert!(!account_plan.thaw);
+            assert!(account_plan.instructions.is_empty());
+        }
+    }
+
+    #[test]
+    fn thaws_frozen_account_of_own_mint() {
+        let owner = Pubkey::new_unique();
+        let frozen = token_account()
+            .owner(&owner)
+            .amount(5)
+            .freeze_authority(&owner)
+            .frozen()
+            .build();
+        let config = PlannerConfig {
+            thaw_frozen: true,
+            ..PlannerConfig::default()
+        };
+
+        let plan = plan(&owner, vec![frozen], Vec::new(), &config);
+
+        let account_plan = &plan.accounts[0];
+        assert!(account_plan.thaw);
+        assert_eq!(
+            account_plan.action,
+            AccountAction::BurnAndClose { amount: 5 }
+        );
+        let instructions: Vec<_> = account_plan
+            .instructions
+            .iter()
+            .map(|instruction| TokenInstruction::unpack(&instruction.data).unwrap())
+            .collect();
+        assert_eq!(
+            instructions,
+            [
+                TokenInstruction::ThawAccount,
+                TokenInstruction::Burn { amount: 5 },
+                TokenInstruction::CloseAccount,
+            ]
+        );
+        assert_eq!(account_plan.instructions[0].accounts[2].pubkey, owner);
+    }
+
+    #[test]
+    fn unwraps_wrapped_sol_instead_of_burning() {
+        let owner = Pubkey::new_unique();
+        let wrapped_sol = token_account().owner(&owner).wrapped_sol(1_000_000).build();
+        let empty = token_account().owner(&owner).build();
+
+        let plan = plan(
+            &owner,
+            vec![wrapped_sol, empty],
+            Vec::new(),
+            &PlannerConfig::default(),
+        );
+
+        let account_plan = &plan.accounts[0];
+        assert_eq!(
+            account_plan.action,
+            AccountAction::Unwrap { amount: 1_000_000 }
+        );
+        assert_eq!(account_plan.instructions.len(), 1);
+        assert_eq!(
+            TokenInstruction::unpack(&account_plan.instructions[0].data).unwrap(),
+            TokenInstruction::CloseAccount
+        );
+        assert_eq!(
+            account_plan.recovered_lamports(),
+            TOKEN_ACCOUNT_RENT + 1_000_000
+        );
+        assert_eq!(plan.unwrapped_lamports(), 1_000_000);
+        assert_eq!(plan.rent_lamports(), 2 * TOKEN_ACCOUNT_RENT);
+        assert_eq!(plan.expected_lamports(), 2 * TOKEN_ACCOUNT_RENT + 1_000_000);
+    }
+
+    #[test]
+    fn revokes_delegates_of_kept_accounts_only() {
+        let owner = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let delegated = || {
+            token_account()
+                .owner(&owner)
+                .delegate(&delegate, 5)
+                .decimals(0)
+        };
+        let kept_nft = delegated().nft().build();
+        let not_empty = delegated().amount(5).build();
+        let frozen = delegated().amount(5).frozen().build();
+        let frozen_empty = delegated().frozen().build();
+        let filtered = delegated().decimals(6).amount(5).build();
+        let closed = delegated().build();
+        let config = PlannerConfig {
+            filter: Some(Filter::parse("decimals == 0").unwrap()),
+            close_empty_only: true,
+            revoke_delegates: true,
+            ..PlannerConfig::default()
+        };
+
+        let plan = plan(
+            &owner,
+            vec![kept_nft, not_empty, frozen, frozen_empty, filtered, closed],
+            Vec::new(),
+            &config,
+        );
+
+        let revoked: Vec<_> = plan
+            .accounts
+            .iter()
+            .map(|account_plan| {
+                let revokes = account_plan
+                    .instructions
+                    .iter()
+                    .filter(|instruction| {
+                        TokenInstruction::unpack(&instruction.data)
+                            .is_ok_and(|instruction| instruction == TokenInstruction::Revoke)
+                    })
+                    .count();
+                assert_eq!(revokes == 1, account_plan.revoke_delegate);
+                revokes
+            })
+            .collect();
+        assert_eq!(revoked, [1, 1, 0, 0, 0, 0]);
+        // Kept for its balance, but frozen all the same
+        assert_eq!(
+            plan.accounts[2].action,
+            AccountAction::Skip(SkipReason::NotEmpty { amount: 5 })
+        );
+        assert_eq!(
+            plan.accounts[3].action,
+            AccountAction::Skip(SkipReason::Frozen { can_thaw: false })
+        );
+        assert_eq!(
+            plan.accounts[4].action,
+            AccountAction::Skip(SkipReason::Filtered)
+        );
+        assert_eq!(plan.accounts[5].action, AccountAction::Close);
+    }
+}
@@ -0,0 +1,349 @@
+use crate::discovery::DiscoveredAccounts;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use log::{info, warn};
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+/// Default endpoint of the Jupiter price API.
+pub const JUPITER_PRICE_API_URL: &str = "https://lite-api.jup.ag/price/v2";
+
+/// Maximum number of mints requested from the Jupiter price API at once.
+const MAX_PRICE_IDS: usize = 100;
+
+/// A source of USD prices per whole token.
+#[async_trait]
+pub trait PriceSource: Send + Sync {
+    /// Returns the USD price of one whole token for each of `mints` that the
+    /// source knows about. Unknown mints are left out.
+    async fn prices(&self, mints: &[Pubkey]) -> Result<HashMap<Pubkey, f64>>;
+}
+
+/// Prices read from a local JSON file mapping mint addresses to USD prices:
+///
+/// ```json
+/// { "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v": 1.0 }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct JsonFilePriceSource {
+    prices: HashMap<Pubkey, f64>,
+}
+
+impl JsonFilePriceSource {
+    /// Loads a price file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read price file {}", path.display()))?;
+        let entries: HashMap<String, f64> = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse price file {}", path.display()))?;
+
+        let mut prices = HashMap::with_capacity(entries.len());
+        for (mint, price) in entries {
+            let mint = Pubkey::from_str(&mint)
+                .with_context(|| format!("Invalid mint {} in {}", mint, path.display()))?;
+            prices.insert(mint, price);
+        }
+
+        Ok(Self { prices })
+    }
+}
+
+#[async_trait]
+impl PriceSource for JsonFilePriceSource {
+    async fn prices(&self, mints: &[Pubkey]) -> Result<HashMap<Pubkey, f64>> {
+        Ok(mints
+            .iter()
+            .filter_map(|mint| self.prices.get(mint).map(|price| (*mint, *price)))
+            .collect())
+    }
+}
+
+/// Prices from a Jupiter-style HTTP price API.
+///
+/// Sends `GET {base_url}?ids=<mint>,<mint>,...` and expects
+/// `{ "data": { "<mint>": { "price": "1.23" } } }`, with `null` for mints
+/// without a price.
+#[derive(Debug, Clone)]
+pub struct JupiterPriceSource {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl JupiterPriceSource {
+    /// Uses the API at `base_url`, for example [`JUPITER_PRICE_API_URL`] or a
+    /// local mock server.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl Default for JupiterPriceSource {
+    fn default() -> Self {
+        Self::new(JUPITER_PRICE_API_URL)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JupiterPriceResponse {
+    data: HashMap<String, Option<JupiterPrice>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JupiterPrice {
+    price: String,
+}
+
+#[async_trait]
+impl PriceSource for JupiterPriceSource {
+    async fn prices(&self, mints: &[Pubkey]) -> Result<HashMap<Pubkey, f64>> {
+        let mut prices = HashMap::with_capacity(mints.len());
+
+        for chunk in mints.chunks(MAX_PRICE_IDS) {
+            let ids = chunk
+                .iter()
+                .map(Pubkey::to_string)
+                .collect::<Vec<_>>()
+                .join(",");
+
+            let response: JupiterPriceResponse = self
+                .client
+                .get(&self.base_url)
+                .query(&[("ids", ids)])
+                .send()
+                .await
+                .context("Failed to request prices")?
+                .error_for_status()
+                .context("Price API returned an error")?
+                .json()
+                .await
+                .context("Failed to parse price API response")?;
+
+            for (mint, price) in response.data {
+                let Some(price) = price else {
+                    continue;
+                };
+                match (Pubkey::from_str(&mint), price.price.parse::<f64>()) {
+                    (Ok(mint), Ok(price)) => {
+                        prices.insert(mint, price);
+                    }
+                    _ => warn!("Ignoring malformed price for {}: {}", mint, price.price),
+                }
+            }
+        }
+
+        Ok(prices)
+    }
+}
+
+/// Looks up the USD price of every discovered mint and records it on the
+/// token accounts holding it.
+pub async fn apply_prices(
+    discovered: &mut DiscoveredAccounts,
+    price_source: &dyn PriceSource,
+) -> Result<()> {
+    let mut mints: Vec<_> = discovered
+        .token_accounts
+        .iter()
+        .map(|token_account| token_account.account.mint)
+        .collect();
+    mints.sort();
+    mints.dedup();
+
+    let prices = price_source.prices(&mints).await?;
+    info!(
+        "Found USD prices for {} of {} mints",
+        prices.len(),
+        mints.len()
+    );
+
+    for token_account in &mut discovered.token_accounts {
+        token_account.usd_price = prices.get(&token_account.account.mint).copied();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    /// Serves one canned `(status, body)` response per request on a local
+    /// port, then returns the `ids` requested each time.
+    async fn serve(responses: Vec<(u16, String)>) -> (String, JoinHandle<Vec<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/price", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let mut requested = Vec::new();
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                while !request.ends_with(b"\r\n\r\n") {
+                    let mut buffer = [0; 1024];
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    assert!(read > 0, "connection closed mid-request");
+                    request.extend_from_slice(&buffer[..read]);
+                }
+
+                let request = String::from_utf8(request).unwrap();
+                let target = request.split(' ').nth(1).unwrap();
+                let ids = target
+                    .split_once("?ids=")
+                    .map(|(_, ids)| ids.replace("%2C", ","))
+                    .unwrap_or_default();
+                requested.push(ids.split(',').map(str::to_string).collect());
+
+                let response = format!(
+                    "HTTP/1.1 {} Canned\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+            requested
+        });
+
+        (url, server)
+    }
+
+    /// Writes `contents` to a fresh price file in the temporary directory.
+    fn price_file(contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "prices-{}-{}.json",
+            std::process::id(),
+            Pubkey::new_unique()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn loads_price_file() {
+        let priced = Pubkey::new_unique();
+        let free = Pubkey::new_unique();
+        let path = price_file(&format!(r#"{{"{}": 0.5, "{}": 0}}"#, priced, free));
+
+        let prices = JsonFilePriceSource::load(&path)
+            .unwrap()
+            .prices(&[priced, free, Pubkey::new_unique()])
+            .await
+            .unwrap();
+
+        assert_eq!(prices, HashMap::from([(priced, 0.5), (free, 0.0)]));
+    }
+
+    #[test]
+    fn rejects_malformed_price_file() {
+        let mint = Pubkey::new_unique();
+        for contents in [
+            "[1.5]".to_string(),
+            format!(r#"{{"{}": "1.5"}}"#, mint),
+            format!(r#"{{"{}": 1.5"#, mint),
+        ] {
+            let path = price_file(&contents);
+
+            let error = JsonFilePriceSource::load(&path).unwrap_err();
+
+            assert_eq!(
+                error.to_string(),
+                format!("Failed to parse price file {}", path.display())
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_mint_in_price_file() {
+        let path = price_file(r#"{"not-a-mint": 1.5}"#);
+
+        let error = JsonFilePriceSource::load(&path).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!("Invalid mint not-a-mint in {}", path.display())
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_null_and_malformed_prices() {
+        let priced = Pubkey::new_unique();
+        let unpriced = Pubkey::new_unique();
+        let malformed = Pubkey::new_unique();
+        let body = serde_json::json!({
+            "data": {
+                priced.to_string(): { "id": priced.to_string(), "price": "1.25" },
+                unpriced.to_string(): null,
+                malformed.to_string(): { "price": "n/a" },
+                "not-a-mint": { "price": "2" },
+            }
+        });
+        let (url, server) = serve(vec![(200, body.to_string())]).await;
+
+        let prices = JupiterPriceSource::new(url)
+            .prices(&[priced, unpriced, malformed])
+            .await
+            .unwrap();
+
+        assert_eq!(prices, HashMap::from([(priced, 1.25)]));
+        assert_eq!(
+            server.await.unwrap(),
+            vec![vec![
+                priced.to_string(),
+                unpriced.to_string(),
+                malformed.to_string()
+            ]]
+        );
+    }
+
+    #[tokio::test]
+    async fn chunks_requests() {
+        let mints: Vec<_> = (0..2 * MAX_PRICE_IDS + 1)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+        let last = mints[2 * MAX_PRICE_IDS];
+        let responses = vec![
+            (200, r#"{ "data": {} }"#.to_string()),
+            (200, r#"{ "data": {} }"#.to_string()),
+            (
+                200,
+                format!(r#"{{ "data": {{ "{}": {{ "price": "3" }} }} }}"#, last),
+            ),
+        ];
+        let (url, server) = serve(responses).await;
+
+        let prices = JupiterPriceSource::new(url).prices(&mints).await.unwrap();
+
+        assert_eq!(prices, HashMap::from([(last, 3.0)]));
+        let requested = server.await.unwrap();
+        assert_eq!(
+            requested.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![MAX_PRICE_IDS, MAX_PRICE_IDS, 1]
+        );
+        let expected: Vec<_> = mints.iter().map(Pubkey::to_string).collect();
+        assert_eq!(requested.concat(), expected);
+    }
+
+    #[tokio::test]
+    async fn fails_on_error_status() {
+        let (url, server) = serve(vec![(503, r#"{ "error": "busy" }"#.to_string())]).await;
+
+        let error = JupiterPriceSource::new(url)
+            .prices(&[Pubkey::new_unique()])
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "Price API returned an error");
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+use crate::registry::Cluster;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// What a protection rule matched on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtectedKind {
+    /// Every token account of the mint is protected.
+    Mint,
+    /// A single token account is protected.
+    Account,
+}
+
+/// Where a protection rule came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleSource {
+    /// A well-known mint from the built-in registry.
+    Builtin { name: String, cluster: Cluster },
+    /// Listed in a protect file.
+    File { path: PathBuf },
+}
+
+/// The rule that kept an account from being burned or closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtectionRule {
+    pub kind: ProtectedKind,
+    pub source: RuleSource,
+}
+
+impl fmt::Display for ProtectionRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ProtectedKind::Mint => "protected mint",
+            ProtectedKind::Account => "protected account",
+        };
+        match &self.source {
+            RuleSource::Builtin { name, cluster } => {
+                write!(f, "{} (built-in {}: {})", kind, cluster, name)
+            }
+            RuleSource::File { path } => write!(f, "{} (listed in {})", kind, path.display()),
+        }
+    }
+}
+
+/// Contents of a protect file, in JSON or TOML:
+///
+/// ```toml
+/// mints = ["EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"]
+/// accounts = ["<token account address>"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProtectFile {
+    #[serde(default)]
+    mints: Vec<String>,
+    #[serde(default)]
+    accounts: Vec<String>,
+}
+
+/// Mints and token accounts that must never be burned or closed.
+#[derive(Debug, Clone, Default)]
+pub struct ProtectionRules {
+    mints: HashMap<Pubkey, ProtectionRule>,
+    accounts: HashMap<Pubkey, ProtectionRule>,
+}
+
+impl ProtectionRules {
+    /// Rules with nothing protected.
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Rules protecting the well-known valuable mints of `cluster`.
+    pub fn builtin(cluster: Cluster) -> Self {
+        let mut rules = Self::empty();
+        for known_mint in cluster.known_mints() {
+            rules.protect_mint(
+                known_mint.pubkey(),
+                RuleSource::Builtin {
+                    name: known_mint.symbol.to_string(),
+                    cluster,
+                },
+            );
+        }
+        rules
+    }
+
+    /// Protects every token account of `mint`. An existing rule for the same
+    /// mint is kept.
+    pub fn protect_mint(&mut self, mint: Pubkey, source: RuleSource) {
+        self.mints.entry(mint).or_insert(ProtectionRule {
+            kind: ProtectedKind::Mint,
+            source,
+        });
+    }
+
+    /// Protects a single token account. An existing rule for the same account
+    /// is kept.
+    pub fn protect_account(&mut self, address: Pubkey, source: RuleSource) {
+        self.accounts.entry(address).or_insert(ProtectionRule {
+            kind: ProtectedKind::Account,
+            source,
+        });
+    }
+
+    /// Removes the protection of `mint`, returning `true` if it was protected.
+    pub fn unprotect_mint(&mut self, mint: &Pubkey) -> bool {
+        self.mints.remove(mint).is_some()
+    }
+
+    /// Merges the mints and accounts listed in a protect file. Files ending in
+    /// `.toml` are parsed as TOML, anything else as JSON.
+    pub fn load_file(&mut self, path: &Path) -> Result<()> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read protect file {}", path.display()))?;
+
+        let file: ProtectFile = if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&contents)
+                .with_context(|| format!("Failed to parse protect file {}", path.display()))?
+        } else {
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse protect file {}", path.display()))?
+        };
+
+        let source = RuleSource::File {
+            path: path.to_path_buf(),
+        };
+        for mint in &file.mints {
+            let mint = Pubkey::from_str(mint)
+                .with_context(|| format!("Invalid mint {} in {}", mint, path.display()))?;
+            self.protect_mint(mint, source.clone());
+        }
+        for account in &file.accounts {
+            let account = Pubkey::from_str(account)
+                .with_context(|| format!("Invalid account {} in {}", account, path.display()))?;
+            self.protect_account(account, source.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Returns the rule protecting the token account `address` holding
+    /// `mint`, if any. Account rules take precedence over mint rules.
+    pub fn check(&self, address: &Pubkey, mint: &Pubkey) -> Option<&ProtectionRule> {
+        self.accounts.get(address).or_else(|| self.mints.get(mint))
+    }
+
+    /// Number of protected mints.
+    pub fn mint_count(&self) -> usize {
+        self.mints.len()
+    }
+
+    /// Number of protected token accounts.
+    pub fn account_count(&self) -> usize {
+        self.accounts.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Writes `contents` to `name` in a fresh directory under the temporary
+    /// directory.
+    fn protect_file(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "protect-{}-{}",
+            std::process::id(),
+            Pubkey::new_unique()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn file_rule(kind: ProtectedKind, path: &Path) -> ProtectionRule {
+        ProtectionRule {
+            kind,
+            source: RuleSource::File {
+                path: path.to_path_buf(),
+            },
+        }
+    }
+
+    #[test]
+    fn loads_json_file() {
+        let mint = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+        let path = protect_file(
+            "protect.json",
+            &format!(r#"{{"mints": ["{}"], "accounts": ["{}"]}}"#, mint, account),
+        );
+
+        let mut rules = ProtectionRules::empty();
+        rules.load_file(&path).unwrap();
+
+        assert_eq!((rules.mint_count(), rules.account_count()), (1, 1));
+        assert_eq!(
+            rules.check(&Pubkey::new_unique(), &mint),
+            Some(&file_rule(ProtectedKind::Mint, &path))
+        );
+        assert_eq!(
+            rules.check(&account, &Pubkey::new_unique()),
+            Some(&file_rule(ProtectedKind::Account, &path))
+        );
+        assert_eq!(
+            rules.check(&Pubkey::new_unique(), &Pubkey::new_unique()),
+            None
+        );
+    }
+
+    #[test]
+    fn loads_toml_file() {
+        let mints = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let path = protect_file(
+            "protect.toml",
+            &format!("mints = [\"{}\", \"{}\"]\n", mints[0], mints[1]),
+        );
+
+        let mut rules = ProtectionRules::empty();
+        rules.load_file(&path).unwrap();
+
+        assert_eq!((rules.mint_count(), rules.account_count()), (2, 0));
+        for mint in &mints {
+            assert_eq!(
+                rules.check(&Pubkey::new_unique(), mint),
+                Some(&file_rule(ProtectedKind::Mint, &path))
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let json = protect_file("protect.json", r#"{"mint": []}"#);
+        let toml = protect_file("protect.toml", "owners = []\n");
+
+        for path in [json, toml] {
+            let error = ProtectionRules::empty().load_file(&path).unwrap_err();
+            assert!(
+                format!("{:#}", error).contains("unknown field"),
+                "{:#}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_address() {
+        let path = protect_file("protect.json", r#"{"accounts": ["not-a-pubkey"]}"#);
+
+        let error = ProtectionRules::empty().load_file(&path).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("Invalid account not-a-pubkey in "));
+    }
+
+    #[test]
+    fn account_rule_takes_precedence_over_mint_rule() {
+        let mint = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+        let path = protect_file("protect.toml", &format!("accounts = [\"{}\"]\n", account));
+
+        let mut rules = ProtectionRules::builtin(Cluster::MainnetBeta);
+        rules.protect_mint(
+            mint,
+            RuleSource::Builtin {
+                name: "TEST".to_string(),
+                cluster: Cluster::MainnetBeta,
+            },
+        );
+        rules.load_file(&path).unwrap();
+
+        assert_eq!(
+            rules.check(&account, &mint),
+            Some(&file_rule(ProtectedKind::Account, &path))
+        );
+        assert_eq!(
+            rules.check(&Pubkey::new_unique(), &mint).unwrap().kind,
+            ProtectedKind::Mint
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{bail, Context, Result};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{hash::Hash, pubkey::Pubkey};
+use std::{fmt, str::FromStr};
+
+const MAINNET_BETA_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+const DEVNET_GENESIS_HASH: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
+const TESTNET_GENESIS_HASH: &str = "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY";
+
+/// A Solana cluster, identified by its genesis hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cluster {
+    MainnetBeta,
+    Devnet,
+    Testnet,
+    /// A local validator or any other cluster without known mints.
+    Unknown,
+}
+
+impl Cluster {
+    /// Maps a genesis hash to the cluster it belongs to.
+    pub fn from_genesis_hash(genesis_hash: &Hash) -> Self {
+        match genesis_hash.to_string().as_str() {
+            MAINNET_BETA_GENESIS_HASH => Cluster::MainnetBeta,
+            DEVNET_GENESIS_HASH => Cluster::Devnet,
+            TESTNET_GENESIS_HASH => Cluster::Testnet,
+            _ => Cluster::Unknown,
+        }
+    }
+
+    /// Detects the cluster `rpc_client` is connected to.
+    pub async fn detect(rpc_client: &RpcClient) -> Result<Self> {
+        let genesis_hash = rpc_client
+            .get_genesis_hash()
+            .await
+            .context("Failed to fetch genesis hash")?;
+        Ok(Self::from_genesis_hash(&genesis_hash))
+    }
+
+    /// Well-known valuable mints on this cluster.
+    pub fn known_mints(&self) -> &'static [KnownMint] {
+        match self {
+            Cluster::MainnetBeta => MAINNET_BETA_MINTS,
+            Cluster::Devnet => DEVNET_MINTS,
+            Cluster::Testnet | Cluster::Unknown => &[],
+        }
+    }
+
+    /// Looks up a well-known mint on this cluster.
+    pub fn known_mint(&self, mint: &Pubkey) -> Option<&'static KnownMint> {
+        self.known_mints()
+            .iter()
+            .find(|known_mint| known_mint.pubkey() == *mint)
+    }
+}
+
+impl fmt::Display for Cluster {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cluster::MainnetBeta => write!(f, "mainnet-beta"),
+            Cluster::Devnet => write!(f, "devnet"),
+            Cluster::Testnet => write!(f, "testnet"),
+            Cluster::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+impl FromStr for Cluster {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "mainnet-beta" | "mainnet" => Ok(Cluster::MainnetBeta),
+            "devnet" => Ok(Cluster::Devnet),
+            "testnet" => Ok(Cluster::Testnet),
+            "unknown" | "localnet" => Ok(Cluster::Unknown),
+            _ => bail!("Unknown cluster: {}", s),
+        }
+    }
+}
+
+/// A well-known mint that is protected by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownMint {
+    /// Ticker symbol.
+    pub symbol: &'static str,
+    /// Mint address.
+    pub address: &'static str,
+}
+
+impl KnownMint {
+    /// The mint address as a [`Pubkey`].
+    pub fn pubkey(&self) -> Pubkey {
+        Pubkey::from_str(self.address).expect("registry mint address is valid")
+    }
+}
+
+const MAINNET_BETA_MINTS: &[KnownMint] = &[
+    KnownMint {
+        symbol: "USDC",
+        address: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+    },
+    KnownMint {
+        symbol: "USDT",
+        address: "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB",
+    },
+    KnownMint {
+        symbol: "PYUSD",
+        address: "2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo",
+    },
+    KnownMint {
+        symbol: "JitoSOL",
+        address: "J1toso1uCk3RLmjorhTtrVwY9HJ7X8V9yYac6Y7kGCPn",
+    },
+    KnownMint {
+        symbol: "mSOL",
+        address: "mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So",
+    },
+    KnownMint {
+        symbol: "bSOL",
+        address: "bSo13r4TkiE4KumL71LsHTPpL2euBYLFx6h9HP3piy1",
+    },
+    KnownMint {
+        symbol: "JUP",
+        address: "JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN",
+    },
+];
+
+const DEVNET_MINTS: &[KnownMint] = &[
+    KnownMint {
+        symbol: "USDC",
+        address: "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU",
+    },
+    KnownMint {
+        symbol: "PYUSD",
+        address: "CXk2AMBfi3TwaEL2468s6zP8xq9NxTXjp9gjMgzeUynM",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const CLUSTERS: [Cluster; 4] = [
+        Cluster::MainnetBeta,
+        Cluster::Devnet,
+        Cluster::Testnet,
+        Cluster::Unknown,
+    ];
+
+    #[test]
+    fn parses_every_known_mint() {
+        for cluster in CLUSTERS {
+            let mints: HashSet<_> = cluster
+                .known_mints()
+                .iter()
+                .map(|known_mint| {
+                    let mint = known_mint.pubkey();
+                    assert_eq!(cluster.known_mint(&mint), Some(known_mint));
+                    mint
+                })
+                .collect();
+            assert_eq!(mints.len(), cluster.known_mints().len(), "{}", cluster);
+        }
+    }
+
+    #[test]
+    fn maps_genesis_hashes_to_clusters() {
+        for (genesis_hash, cluster) in [
+            (MAINNET_BETA_GENESIS_HASH, Cluster::MainnetBeta),
+            (DEVNET_GENESIS_HASH, Cluster::Devnet),
+            (TESTNET_GENESIS_HASH, Cluster::Testnet),
+        ] {
+            let genesis_hash = Hash::from_str(genesis_hash).unwrap();
+            assert_eq!(Cluster::from_genesis_hash(&genesis_hash), cluster);
+        }
+    }
+
+    #[test]
+    fn falls_back_to_unknown_cluster() {
+        assert_eq!(
+            Cluster::from_genesis_hash(&Hash::new_unique()),
+            Cluster::Unknown
+        );
+        assert_eq!(
+            Cluster::from_genesis_hash(&Hash::default()),
+            Cluster::Unknown
+        );
+        assert!(Cluster::Unknown.known_mints().is_empty());
+    }
+
+    #[test]
+    fn parses_displayed_cluster_names() {
+        for cluster in CLUSTERS {
+            assert_eq!(cluster.to_string().parse::<Cluster>().unwrap(), cluster);
+        }
+        assert!("mainnet-alpha".parse::<Cluster>().is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{
+    batcher::transaction_footprint,
+    plan::{AccountPlan, CleanupPlan},
+};
+use solana_sdk::native_token::lamports_to_sol;
+use std::fmt::Write;
+
+/// Renders a human-readable description of `plan`, as printed by dry runs.
+pub fn render_plan(plan: &CleanupPlan) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "Cleanup plan for wallet {}", plan.owner).unwrap();
+    writeln!(out).unwrap();
+
+    let (nfts, accounts): (Vec<_>, Vec<_>) =
+        plan.accounts.iter().partition(|account| account.is_nft);
+
+    writeln!(out, "Accounts ({}):", accounts.len()).unwrap();
+    for account in accounts {
+        render_account(&mut out, account);
+    }
+    writeln!(out).unwrap();
+
+    if !nfts.is_empty() {
+        writeln!(out, "NFTs ({}):", nfts.len()).unwrap();
+        for account in nfts {
+            render_account(&mut out, account);
+        }
+        writeln!(out).unwrap();
+    }
+
+    if !plan.mints.is_empty() {
+        writeln!(out, "Mints ({}):", plan.mints.len()).unwrap();
+        for mint in &plan.mints {
+            writeln!(out, "  {}", mint.address).unwrap();
+            writeln!(out, "    supply:  {} raw", mint.supply).unwrap();
+            writeln!(out, "    action:  {}", mint.action).unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+
+    if !plan.unreadable.is_empty() {
+        writeln!(
+            out,
+            "Unreadable accounts, skipped ({}):",
+            plan.unreadable.len()
+        )
+        .unwrap();
+        for account in &plan.unreadable {
+            writeln!(out, "  {}", account.address).unwrap();
+            writeln!(out, "    program: {}", program_name(&account.program_id)).unwrap();
+            writeln!(out, "    reason:  {}", account.reason).unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+
+    writeln!(out, "Batches ({}):", plan.batches.len()).unwrap();
+    if let Some(memo) = &plan.memo {
+        writeln!(out, "  memo: {}", memo).unwrap();
+    }
+    for (index, batch) in plan.batches.iter().enumerate() {
+        let (size, account_keys) =
+            transaction_footprint(&plan.owner, &batch.instructions, plan.memo.as_deref());
+        writeln!(
+            out,
+            "  #{}: {} instructions, {} accounts, {} account keys, {} bytes",
+            index + 1,
+            batch.instructions.len(),
+            batch.accounts.len(),
+            account_keys,
+            size
+        )
+        .unwrap();
+        for address in &batch.accounts {
+            writeln!(out, "    {}", address).unwrap();
+        }
+    }
+    writeln!(out).unwrap();
+
+    let closing = plan
+        .accounts
+        .iter()
+        .filter(|account| account.closes())
+        .count();
+    writeln!(
+        out,
+        "Accounts to close: {} of {}",
+        closing,
+        plan.accounts.len() + plan.unreadable.len()
+    )
+    .unwrap();
+    if !plan.mints.is_empty() {
+        let closing = plan.mints.iter().filter(|mint| mint.closes()).count();
+        writeln!(out, "Mints to close: {} of {}", closing, plan.mints.len()).unwrap();
+    }
+    writeln!(
+        out,
+        "Rent to recover: {} lamports ({} SOL)",
+        plan.rent_lamports(),
+        lamports_to_sol(plan.rent_lamports())
+    )
+    .unwrap();
+    if plan.unwrapped_lamports() > 0 {
+        writeln!(
+            out,
+            "Wrapped SOL to unwrap: {} lamports ({} SOL)",
+            plan.unwrapped_lamports(),
+            lamports_to_sol(plan.unwrapped_lamports())
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "Total returned to wallet: {} lamports ({} SOL)",
+            plan.expected_lamports(),
+            lamports_to_sol(plan.expected_lamports())
+        )
+        .unwrap();
+    }
+
+    out
+}
+
+fn render_account(out: &mut String, account: &AccountPlan) {
+    writeln!(out, "  {}", account.address).unwrap();
+    writeln!(out, "    mint:    {}", account.mint).unwrap();
+    writeln!(out, "    program: {}", program_name(&account.program_id)).unwrap();
+    match account.ui_amount() {
+        Some(ui_amount) => {
+            writeln!(out, "    amount:  {} ({} raw)", ui_amount, account.amount).unwrap()
+        }
+        None => writeln!(out, "    amount:  {} raw", account.amount).unwrap(),
+    }
+    if let Some(usd_value) = account.usd_value {
+        writeln!(out, "    value:   ${:.2}", usd_value).unwrap();
+    }
+    writeln!(
+        out,
+        "    action:  {} ({})",
+        account.action,
+        account.action.reason()
+    )
+    .unwrap();
+    if let Some((delegate, delegated_amount)) = account.delegate {
+        writeln!(
+            out,
+            "    delegate: {} ({} tokens{})",
+            delegate,
+            delegated_amount,
+            if account.revoke_delegate {
+                ", will be revoked"
+            } else {
+                ""
+            }
+        )
+        .unwrap();
+    }
+    if let Some(close_authority) = account.close_authority {
+        writeln!(out, "    close authority: {}", close_authority).unwrap();
+    }
+    if !account.mint_risks.is_empty() {
+        let risks: Vec<_> = account.mint_risks.iter().map(ToString::to_string).collect();
+        writeln!(out, "    mint risks: {}", risks.join(", ")).unwrap();
+    }
+    if account.withheld_amount > 0 {
+        writeln!(
+            out,
+            "    withheld fees: {} raw{}",
+            account.withheld_amount,
+            if account.harvest {
+                ", harvested to the mint first"
+            } else {
+                ""
+            }
+        )
+        .unwrap();
+    }
+    if account.empty_confidential {
+        writeln!(
+            out,
+            "    confidential balance: zero, applied and emptied first"
+        )
+        .unwrap();
+    }
+    if account.thaw {
+        writeln!(out, "    thaw:    yes (wallet is the freeze authority)").unwrap();
+    }
+}
+
+fn program_name(program_id: &solana_sdk::pubkey::Pubkey) -> &'static str {
+    if *program_id == spl_token_2022::id() {
+        "Token-2022"
+    } else {
+        "SPL Token"
+    }
+}
@@ -0,0 +1,199 @@
+use crate::discovery::optional_pubkey;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::extension::ExtensionType;
+use std::fmt;
+
+/// `ExtensionType::Pausable`, which is newer than the `spl-token-2022`
+/// release this crate builds against.
+const PAUSABLE_EXTENSION_TYPE: u16 = 26;
+
+/// A Token-2022 mint extension that affects whether burning and closing
+/// succeed, or that is common among scam airdrops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MintRisk {
+    /// A permanent delegate can transfer or burn tokens from every account of
+    /// the mint.
+    PermanentDelegate { delegate: Pubkey },
+    /// Tokens cannot be transferred, only burned.
+    NonTransferable,
+    /// Every transfer invokes a program chosen by the mint.
+    TransferHook { program_id: Pubkey },
+    /// The pause authority can block transfers and burns.
+    Pausable { paused: bool },
+}
+
+impl MintRisk {
+    /// Returns `true` for extensions typical of unsolicited airdrops: tokens
+    /// that can be taken back, cannot be moved, or run arbitrary code when
+    /// moved.
+    pub fn is_suspicious(&self) -> bool {
+        !matches!(self, MintRisk::Pausable { .. })
+    }
+
+    /// Returns `true` if the balance cannot be burned right now.
+    pub fn blocks_burn(&self) -> bool {
+        matches!(self, MintRisk::Pausable { paused: true })
+    }
+}
+
+impl fmt::Display for MintRisk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MintRisk::PermanentDelegate { delegate } => {
+                write!(f, "permanent delegate {}", delegate)
+            }
+            MintRisk::NonTransferable => write!(f, "non-transferable"),
+            MintRisk::TransferHook { program_id } => {
+                write!(f, "transfer hook program {}", program_id)
+            }
+            MintRisk::Pausable { paused: true } => write!(f, "paused"),
+            MintRisk::Pausable { paused: false } => write!(f, "pausable"),
+        }
+    }
+}
+
+/// Flags the risky extensions among a mint's raw TLV `entries`.
+///
+/// Values are read by hand so mints using extensions unknown to
+/// `spl-token-2022` are still understood. Extensions whose key is unset, such
+/// as a permanent delegate of `None`, are not flagged.
+pub fn mint_risks(entries: &[(u16, &[u8])]) -> Vec<MintRisk> {
+    let permanent_delegate = u16::from(ExtensionType::PermanentDelegate);
+    let non_transferable = u16::from(ExtensionType::NonTransferable);
+    let transfer_hook = u16::from(ExtensionType::TransferHook);
+
+    entries
+        .iter()
+        .filter_map(|&(extension_type, value)| match extension_type {
+            // delegate: OptionalNonZeroPubkey
+            t if t == permanent_delegate => {
+                optional_pubkey(value, 0).map(|delegate| MintRisk::PermanentDelegate { delegate })
+            }
+            t if t == non_transferable => Some(MintRisk::NonTransferable),
+            // authority: OptionalNonZeroPubkey, program_id: OptionalNonZeroPubkey
+            t if t == transfer_hook => {
+                optional_pubkey(value, 32).map(|program_id| MintRisk::TransferHook { program_id })
+            }
+            // authority: OptionalNonZeroPubkey, paused: PodBool
+            PAUSABLE_EXTENSION_TYPE => Some(MintRisk::Pausable {
+                paused: value.get(32).is_some_and(|&paused| paused != 0),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_token_2022::{
+        extension::{
+            permanent_delegate::PermanentDelegate, transfer_hook::TransferHook,
+            BaseStateWithExtensions, BaseStateWithExtensionsMut, Extension, StateWithExtensions,
+            StateWithExtensionsMut,
+        },
+        state::Mint,
+    };
+
+    /// The raw value of a `V` extension initialized by `init` in a packed
+    /// mint, as `extension_entries` reads it.
+    fn packed<V: Extension + bytemuck::Pod + Default>(init: impl FnOnce(&mut V)) -> Vec<u8> {
+        let len = ExtensionType::try_calculate_account_len::<Mint>(&[V::TYPE]).unwrap();
+        let mut data = vec![0; len];
+        let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        init(state.init_extension::<V>(true).unwrap());
+        state.base = Mint {
+            is_initialized: true,
+            ..Mint::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+
+        StateWithExtensions::<Mint>::unpack(&data)
+            .unwrap()
+            .get_extension_bytes::<V>()
+            .unwrap()
+            .to_vec()
+    }
+
+    #[test]
+    fn reads_permanent_delegate() {
+        let delegate = Pubkey::new_unique();
+        let value = packed::<PermanentDelegate>(|extension| {
+            extension.delegate = Some(delegate).try_into().unwrap();
+        });
+
+        assert_eq!(
+            mint_risks(&[(u16::from(ExtensionType::PermanentDelegate), &value)]),
+            [MintRisk::PermanentDelegate { delegate }]
+        );
+    }
+
+    #[test]
+    fn ignores_unset_permanent_delegate() {
+        let value = packed::<PermanentDelegate>(|_| {});
+
+        assert!(mint_risks(&[(u16::from(ExtensionType::PermanentDelegate), &value)]).is_empty());
+    }
+
+    #[test]
+    fn reads_transfer_hook_program_after_authority() {
+        let program_id = Pubkey::new_unique();
+        let value = packed::<TransferHook>(|extension| {
+            extension.authority = Some(Pubkey::new_unique()).try_into().unwrap();
+            extension.program_id = Some(program_id).try_into().unwrap();
+        });
+
+        assert_eq!(
+            mint_risks(&[(u16::from(ExtensionType::TransferHook), &value)]),
+            [MintRisk::TransferHook { program_id }]
+        );
+    }
+
+    #[test]
+    fn ignores_transfer_hook_without_program() {
+        let value = packed::<TransferHook>(|extension| {
+            extension.authority = Some(Pubkey::new_unique()).try_into().unwrap();
+        });
+
+        assert!(mint_risks(&[(u16::from(ExtensionType::TransferHook), &value)]).is_empty());
+    }
+
+    #[test]
+    fn reads_pause_state_after_authority() {
+        // PausableConfig is newer than the bundled spl-token-2022: an
+        // OptionalNonZeroPubkey authority followed by a PodBool
+        let authority = Pubkey::new_unique();
+        let paused = [authority.as_ref(), &[1]].concat();
+        let resumed = [authority.as_ref(), &[0]].concat();
+
+        assert_eq!(
+            mint_risks(&[(PAUSABLE_EXTENSION_TYPE, &paused)]),
+            [MintRisk::Pausable { paused: true }]
+        );
+        assert_eq!(
+            mint_risks(&[(PAUSABLE_EXTENSION_TYPE, &resumed)]),
+            [MintRisk::Pausable { paused: false }]
+        );
+    }
+
+    #[test]
+    fn skips_harmless_extensions() {
+        let delegate = Pubkey::new_unique();
+        let value = packed::<PermanentDelegate>(|extension| {
+            extension.delegate = Some(delegate).try_into().unwrap();
+        });
+
+        assert_eq!(
+            mint_risks(&[
+                (u16::from(ExtensionType::MintCloseAuthority), &[0; 32]),
+                (u16::from(ExtensionType::NonTransferable), &[]),
+                (u16::from(ExtensionType::PermanentDelegate), &value),
+            ]),
+            [
+                MintRisk::NonTransferable,
+                MintRisk::PermanentDelegate { delegate }
+            ]
+        );
+    }
+}
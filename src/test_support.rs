@@ -0,0 +1,145 @@
+//! Fixtures shared by the unit tests.
+
+use crate::{
+    confidential::ConfidentialBalance,
+    discovery::{MintInfo, TokenAccountInfo},
+    risk::MintRisk,
+};
+use solana_sdk::{program_option::COption, pubkey::Pubkey};
+use spl_token_2022::{
+    native_mint,
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+
+/// Rent-exempt balance of a classic SPL Token account.
+pub(crate) const TOKEN_ACCOUNT_RENT: u64 = 2_039_280;
+
+/// Starts building an empty SPL Token account of a fresh wallet and a fresh
+/// mint with 6 decimals and a supply of 1,000 whole tokens.
+pub(crate) fn token_account() -> TokenAccountBuilder {
+    let mint = Pubkey::new_unique();
+    TokenAccountBuilder(TokenAccountInfo {
+        address: Pubkey::new_unique(),
+        program_id: spl_token::id(),
+        lamports: TOKEN_ACCOUNT_RENT,
+        account: TokenAccount {
+            mint,
+            owner: Pubkey::new_unique(),
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        },
+        extensions: Vec::new(),
+        withheld_amount: 0,
+        confidential: None,
+        mint_info: Some(MintInfo {
+            address: mint,
+            program_id: spl_token::id(),
+            lamports: 1_461_600,
+            mint: Mint {
+                decimals: 6,
+                supply: 1_000_000_000,
+                is_initialized: true,
+                ..Mint::default()
+            },
+            extensions: Vec::new(),
+            risks: Vec::new(),
+            close_authority: None,
+        }),
+        usd_price: None,
+        metaplex: None,
+        last_activity: None,
+    })
+}
+
+/// Overrides the defaults of [`token_account`].
+pub(crate) struct TokenAccountBuilder(TokenAccountInfo);
+
+impl TokenAccountBuilder {
+    pub(crate) fn owner(mut self, owner: &Pubkey) -> Self {
+        self.0.account.owner = *owner;
+        self
+    }
+
+    /// Moves the account and its mint to `program_id`.
+    pub(crate) fn program_id(mut self, program_id: &Pubkey) -> Self {
+        self.0.program_id = *program_id;
+        self.mint_info().program_id = *program_id;
+        self
+    }
+
+    pub(crate) fn amount(mut self, amount: u64) -> Self {
+        self.0.account.amount = amount;
+        self
+    }
+
+    pub(crate) fn decimals(mut self, decimals: u8) -> Self {
+        self.mint_info().mint.decimals = decimals;
+        self
+    }
+
+    pub(crate) fn supply(mut self, supply: u64) -> Self {
+        self.mint_info().mint.supply = supply;
+        self
+    }
+
+    /// Holds the only token of a mint with no decimals.
+    pub(crate) fn nft(self) -> Self {
+        self.decimals(0).supply(1).amount(1)
+    }
+
+    /// Wraps `amount` lamports of SOL on top of the rent.
+    pub(crate) fn wrapped_sol(mut self, amount: u64) -> Self {
+        self.0.account.mint = native_mint::id();
+        self.0.account.is_native = COption::Some(TOKEN_ACCOUNT_RENT);
+        self.0.account.amount = amount;
+        self.0.lamports = TOKEN_ACCOUNT_RENT + amount;
+        let mint_info = self.mint_info();
+        mint_info.address = native_mint::id();
+        mint_info.mint.decimals = native_mint::DECIMALS;
+        mint_info.mint.supply = 0;
+        self
+    }
+
+    pub(crate) fn delegate(mut self, delegate: &Pubkey, amount: u64) -> Self {
+        self.0.account.delegate = COption::Some(*delegate);
+        self.0.account.delegated_amount = amount;
+        self
+    }
+
+    pub(crate) fn freeze_authority(mut self, authority: &Pubkey) -> Self {
+        self.mint_info().mint.freeze_authority = COption::Some(*authority);
+        self
+    }
+
+    pub(crate) fn mint_risks(mut self, risks: Vec<MintRisk>) -> Self {
+        self.mint_info().risks = risks;
+        self
+    }
+
+    pub(crate) fn frozen(mut self) -> Self {
+        self.0.account.state = AccountState::Frozen;
+        self
+    }
+
+    pub(crate) fn confidential(mut self, confidential: ConfidentialBalance) -> Self {
+        self.0.confidential = Some(confidential);
+        self
+    }
+
+    /// Leaves the mint out, as when it could not be fetched.
+    pub(crate) fn unknown_mint(mut self) -> Self {
+        self.0.mint_info = None;
+        self
+    }
+
+    pub(crate) fn build(self) -> TokenAccountInfo {
+        self.0
+    }
+
+    fn mint_info(&mut self) -> &mut MintInfo {
+        self.0
+            .mint_info
+            .as_mut()
+            .expect("mint of the test account was left out")
+    }
+}
@@ -0,0 +1,412 @@
+use crate::{
+    plan::{AccountAction, AccountPlan, CleanupPlan},
+    registry::Cluster,
+};
+use anyhow::{Context, Result};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+use solana_sdk::{native_token::lamports_to_sol, pubkey::Pubkey};
+use std::collections::HashSet;
+
+/// Groups of accounts that can be toggled together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Category {
+    /// Fungible balances that will be burned.
+    Burn,
+    /// NFTs that will be burned.
+    Nft,
+    /// Accounts that are already empty.
+    Empty,
+    /// Wrapped SOL accounts that will be unwrapped.
+    WrappedSol,
+}
+
+impl Category {
+    fn of(account: &AccountPlan) -> Option<Self> {
+        match account.action {
+            AccountAction::BurnNft { .. } => Some(Category::Nft),
+            AccountAction::BurnAndClose { .. } if account.is_nft => Some(Category::Nft),
+            AccountAction::BurnAndClose { .. } => Some(Category::Burn),
+            AccountAction::Close => Some(Category::Empty),
+            AccountAction::Unwrap { .. } => Some(Category::WrappedSol),
+            AccountAction::Skip(_) => None,
+        }
+    }
+}
+
+/// Selection state of the account list.
+struct App<'a> {
+    plan: &'a CleanupPlan,
+    cluster: Cluster,
+    selected: Vec<bool>,
+    table_state: TableState,
+}
+
+impl App<'_> {
+    fn toggle(&mut self, index: usize) {
+        if self
+            .plan
+            .accounts
+            .get(index)
+            .is_some_and(AccountPlan::closes)
+        {
+            self.selected[index] = !self.selected[index];
+        }
+    }
+
+    /// Deselects the whole category if any of it is selected, and selects all
+    /// of it otherwise.
+    fn toggle_category(&mut self, category: Category) {
+        let indices: Vec<_> = self
+            .plan
+            .accounts
+            .iter()
+            .enumerate()
+            .filter(|(_, account)| Category::of(account) == Some(category))
+            .map(|(index, _)| index)
+            .collect();
+        let select = !indices.iter().any(|&index| self.selected[index]);
+        for index in indices {
+            self.selected[index] = select;
+        }
+    }
+
+    fn toggle_all(&mut self) {
+        let select = !self.selected.iter().any(|&selected| selected);
+        for (index, account) in self.plan.accounts.iter().enumerate() {
+            self.selected[index] = select && account.closes();
+        }
+    }
+
+    fn selected_lamports(&self) -> u64 {
+        self.plan
+            .accounts
+            .iter()
+            .zip(&self.selected)
+            .filter(|(_, &selected)| selected)
+            .map(|(account, _)| account.recovered_lamports())
+            .sum()
+    }
+
+    fn selection(&self) -> HashSet<Pubkey> {
+        self.plan
+            .accounts
+            .iter()
+            .zip(&self.selected)
+            .filter(|(_, &selected)| selected)
+            .map(|(account, _)| account.address)
+            .collect()
+    }
+}
+
+/// Lets the user review `plan` in the terminal and choose which accounts to
+/// clean up. Every account the plan would close starts out selected; skipped
+/// accounts are listed but cannot be selected.
+///
+/// Returns the selected token accounts, or `None` if the user cancelled.
+pub fn select_accounts(plan: &CleanupPlan, cluster: Cluster) -> Result<Option<HashSet<Pubkey>>> {
+    let mut app = App {
+        plan,
+        cluster,
+        selected: plan.accounts.iter().map(AccountPlan::closes).collect(),
+        table_state: TableState::default(),
+    };
+    if !plan.accounts.is_empty() {
+        app.table_state.select(Some(0));
+    }
+
+    let mut terminal = ratatui::try_init().context("Failed to initialize the terminal")?;
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+    result
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> Result<Option<HashSet<Pubkey>>> {
+    loop {
+        terminal.draw(|frame| draw(frame, app))?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+            KeyCode::Enter => return Ok(Some(app.selection())),
+            KeyCode::Down | KeyCode::Char('j') => app.table_state.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => app.table_state.select_previous(),
+            KeyCode::Char(' ') => {
+                if let Some(index) = app.table_state.selected() {
+                    app.toggle(index);
+                }
+            }
+            KeyCode::Char('a') => app.toggle_all(),
+            KeyCode::Char('b') => app.toggle_category(Category::Burn),
+            KeyCode::Char('n') => app.toggle_category(Category::Nft),
+            KeyCode::Char('e') => app.toggle_category(Category::Empty),
+            KeyCode::Char('w') => app.toggle_category(Category::WrappedSol),
+            _ => {}
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let [header_area, table_area, details_area, help_area] = Layout::vertical([
+        Constraint::Length(2),
+        Constraint::Min(3),
+        Constraint::Length(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let selected_count = app.selected.iter().filter(|&&selected| selected).count();
+    let lamports = app.selected_lamports();
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from(format!("Cleanup plan for wallet {}", app.plan.owner)),
+            Line::from(format!(
+                "Selected {} of {} accounts, recovering {} lamports ({} SOL)",
+                selected_count,
+                app.plan.accounts.len(),
+                lamports,
+                lamports_to_sol(lamports)
+            ))
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+        ]),
+        header_area,
+    );
+
+    let rows = app
+        .plan
+        .accounts
+        .iter()
+        .zip(&app.selected)
+        .map(|(account, &selected)| {
+            let checkbox = match (account.closes(), selected) {
+                (false, _) => "   ",
+                (true, true) => "[x]",
+                (true, false) => "[ ]",
+            };
+            let name = account
+                .name
+                .as_deref()
+                .or_else(|| {
+                    app.cluster
+                        .known_mint(&account.mint)
+                        .map(|known_mint| known_mint.symbol)
+                })
+                .unwrap_or_default()
+                .to_string();
+            let balance = account
+                .ui_amount()
+                .unwrap_or_else(|| format!("{} raw", account.amount));
+            let decimals = account
+                .decimals
+                .map(|decimals| decimals.to_string())
+                .unwrap_or_else(|| "?".to_string());
+            let style = if account.closes() {
+                Style::default()
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+
+            Row::new(vec![
+                Cell::from(checkbox),
+                Cell::from(short_address(&account.address)),
+                Cell::from(short_address(&account.mint)),
+                Cell::from(name),
+                Cell::from(balance),
+                Cell::from(decimals),
+                Cell::from(account.action.to_string()),
+            ])
+            .style(style)
+        });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(3),
+            Constraint::Length(11),
+            Constraint::Length(11),
+            Constraint::Length(16),
+            Constraint::Min(12),
+            Constraint::Length(8),
+            Constraint::Length(10),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "", "Account", "Mint", "Name", "Balance", "Decimals", "Action",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().borders(Borders::TOP | Borders::BOTTOM))
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, table_area, &mut app.table_state);
+
+    let details = app
+        .table_state
+        .selected()
+        .and_then(|index| app.plan.accounts.get(index))
+        .map(|account| {
+            vec![
+                Line::from(format!("Account: {}", account.address)),
+                Line::from(format!("Mint:    {}", account.mint)),
+                Line::from(format!("Reason:  {}", account.action.reason())),
+            ]
+        })
+        .unwrap_or_default();
+    frame.render_widget(Paragraph::new(details), details_area);
+
+    frame.render_widget(
+        Paragraph::new(
+            "↑/↓ move  space toggle  a all  b burns  n NFTs  e empty  w wrapped SOL  enter confirm  q cancel",
+        )
+        .style(Style::default().fg(Color::DarkGray)),
+        help_area,
+    );
+}
+
+/// Shortens an address to its first and last four characters.
+fn short_address(address: &Pubkey) -> String {
+    let address = address.to_string();
+    format!("{}..{}", &address[..4], &address[address.len() - 4..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        discovery::DiscoveredAccounts,
+        planner::{plan_cleanup, NftPolicy, PlannerConfig},
+        test_support::token_account,
+    };
+
+    /// Plans, in order, a burn, an NFT burn, an empty account, wrapped SOL,
+    /// a frozen account that is skipped and a second burn.
+    fn plan() -> CleanupPlan {
+        let owner = Pubkey::new_unique();
+        let discovered = DiscoveredAccounts {
+            token_accounts: vec![
+                token_account().owner(&owner).amount(5).build(),
+                token_account().owner(&owner).nft().build(),
+                token_account().owner(&owner).build(),
+                token_account().owner(&owner).wrapped_sol(1_000).build(),
+                token_account().owner(&owner).frozen().build(),
+                token_account().owner(&owner).amount(7).build(),
+            ],
+            ..DiscoveredAccounts::default()
+        };
+        let config = PlannerConfig {
+            nft_policy: NftPolicy::Burn,
+            ..PlannerConfig::default()
+        };
+        plan_cleanup(&owner, discovered, &config).unwrap()
+    }
+
+    fn app(plan: &CleanupPlan) -> App<'_> {
+        App {
+            plan,
+            cluster: Cluster::MainnetBeta,
+            selected: plan.accounts.iter().map(AccountPlan::closes).collect(),
+            table_state: TableState::default(),
+        }
+    }
+
+    #[test]
+    fn selects_every_closed_account_at_first() {
+        let plan = plan();
+        let app = app(&plan);
+
+        assert_eq!(app.selected, [true, true, true, true, false, true]);
+        assert_eq!(app.selection().len(), 5);
+        assert!(!app.selection().contains(&plan.accounts[4].address));
+        assert_eq!(
+            app.selected_lamports(),
+            plan.accounts
+                .iter()
+                .filter(|account| account.closes())
+                .map(AccountPlan::recovered_lamports)
+                .sum::<u64>()
+        );
+    }
+
+    #[test]
+    fn toggles_closed_accounts_only() {
+        let plan = plan();
+        let mut app = app(&plan);
+
+        app.toggle(0);
+        app.toggle(4);
+        assert_eq!(app.selected, [false, true, true, true, false, true]);
+
+        app.toggle(0);
+        assert_eq!(app.selected, [true, true, true, true, false, true]);
+    }
+
+    #[test]
+    fn ignores_toggle_past_the_end() {
+        let owner = Pubkey::new_unique();
+        let plan = plan_cleanup(
+            &owner,
+            DiscoveredAccounts::default(),
+            &PlannerConfig::default(),
+        )
+        .unwrap();
+        let mut app = app(&plan);
+        app.table_state.select_next();
+
+        app.toggle(0);
+
+        assert!(app.selection().is_empty());
+    }
+
+    #[test]
+    fn toggles_category() {
+        let plan = plan();
+        let mut app = app(&plan);
+
+        app.toggle_category(Category::Burn);
+        assert_eq!(app.selected, [false, true, true, true, false, false]);
+
+        // A partly selected category is deselected first
+        app.toggle(0);
+        app.toggle_category(Category::Burn);
+        assert_eq!(app.selected, [false, true, true, true, false, false]);
+
+        app.toggle_category(Category::Burn);
+        app.toggle_category(Category::Nft);
+        app.toggle_category(Category::WrappedSol);
+        assert_eq!(app.selected, [true, false, true, false, false, true]);
+
+        app.toggle_category(Category::Empty);
+        assert_eq!(app.selected, [true, false, false, false, false, true]);
+    }
+
+    #[test]
+    fn toggles_all_closed_accounts() {
+        let plan = plan();
+        let mut app = app(&plan);
+
+        app.toggle_all();
+        assert_eq!(app.selected, [false; 6]);
+        assert_eq!(app.selected_lamports(), 0);
+
+        app.toggle_all();
+        assert_eq!(app.selected, [true, true, true, true, false, true]);
+
+        // Anything selected makes it deselect everything
+        app.toggle_all();
+        app.toggle(2);
+        app.toggle_all();
+        assert_eq!(app.selected, [false; 6]);
+    }
+}